@@ -1,26 +1,78 @@
 
-use std::net::IpAddr;
+use crate::price_providers::BinancePriceProvider;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 
-const ERR_REDIS_DB_IP: &str = "REDIS_DB_IP is missing or invalid";
+const ERR_REDIS_DB_HOST: &str = "REDIS_DB_HOST is missing or invalid";
 const ERR_REDIS_DB_PORT: &str = "REDIS_DB_PORT is missing or invalid";
+const ERR_REDIS_DB_TLS: &str = "REDIS_DB_TLS must be true or false";
+const ERR_SERVER_ADDR: &str = "SERVER_ADDR is invalid";
+
+/// Address the REST server binds to when `SERVER_ADDR` isn't set.
+pub const DEFAULT_SERVER_ADDR: &str = "0.0.0.0:8080";
+
+/// Tokens seeded into an empty db when `DEFAULT_TOKENS` isn't set.
+/// Tokens are base assets, priced against `QUOTE_ASSET`.
+pub const DEFAULT_TOKENS: [&str; 2] = ["UNI", "ZRX"];
 
 pub struct EnvConfig {
-    pub ip: IpAddr,
+    /// From `REDIS_DB_HOST`, a hostname or IP address, or the older `REDIS_DB_IP` when unset.
+    /// With TLS it must be the name the server certificate was issued for.
+    pub host: String,
     pub port: u16,
+    pub password: Option<String>,
+    /// From `REDIS_DB_TLS`, off when unset.
+    pub use_tls: bool,
+    pub server_addr: SocketAddr,
+    /// From the comma separated `DEFAULT_TOKENS`, `DEFAULT_TOKENS` const when unset.
+    /// Base assets such as `BTC`, not symbols, the quote asset is `quote_asset`.
+    pub default_tokens: Vec<String>,
+    /// From `QUOTE_ASSET` trimmed and uppercased, `BinancePriceProvider::DEFAULT_QUOTE_ASSET` when unset.
+    pub quote_asset: String,
 }
 
 pub fn load_from_env<F>(env_var_fn: F) -> EnvConfig
 where
     F: Fn(&str) -> Result<String, std::env::VarError>,
 {
-    let ip = env_var_fn("REDIS_DB_IP").ok()
-        .and_then(|s| IpAddr::from_str(&s).ok())
-        .expect(ERR_REDIS_DB_IP);
+    let host = env_var_fn("REDIS_DB_HOST")
+        .or_else(|_| env_var_fn("REDIS_DB_IP")).ok()
+        .map(|s| s.trim().to_string())
+        .filter(|host| is_valid_host(host))
+        .expect(ERR_REDIS_DB_HOST);
     let port = env_var_fn("REDIS_DB_PORT").ok()
         .and_then(|s| s.parse::<u16>().ok())
         .expect(ERR_REDIS_DB_PORT);
-    EnvConfig { ip, port }
+    let password = env_var_fn("REDIS_DB_PASSWORD").ok();
+    let use_tls = env_var_fn("REDIS_DB_TLS").ok()
+        .map(|s| s.parse::<bool>().expect(ERR_REDIS_DB_TLS))
+        .unwrap_or(false);
+    let server_addr = env_var_fn("SERVER_ADDR")
+        .unwrap_or_else(|_| DEFAULT_SERVER_ADDR.to_string())
+        .parse::<SocketAddr>()
+        .expect(ERR_SERVER_ADDR);
+    let default_tokens = env_var_fn("DEFAULT_TOKENS")
+        .map(|s| parse_tokens(&s))
+        .unwrap_or_else(|_| DEFAULT_TOKENS.iter().map(|token| token.to_string()).collect());
+    let quote_asset = env_var_fn("QUOTE_ASSET")
+        .map(|s| s.trim().to_uppercase())
+        .unwrap_or_else(|_| BinancePriceProvider::DEFAULT_QUOTE_ASSET.to_string());
+    EnvConfig { host, port, password, use_tls, server_addr, default_tokens, quote_asset }
+}
+
+/// An IP address or a DNS name made of letters, digits, `-` and `.`.
+fn is_valid_host(host: &str) -> bool {
+    IpAddr::from_str(host).is_ok()
+        || !host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+/// Splits a comma separated list, trimming whitespace and dropping empty entries.
+fn parse_tokens(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
 #[cfg(test)]
@@ -29,21 +81,39 @@ mod tests {
     use std::env::VarError;
 
     struct TestEnvVars {
+        host: Option<String>,
         ip: Option<String>,
         port: Option<String>,
+        password: Option<String>,
+        tls: Option<String>,
+        server_addr: Option<String>,
+        default_tokens: Option<String>,
+        quote_asset: Option<String>,
     }
 
     impl TestEnvVars {
         fn good() -> Self {
             Self {
-                ip: Some("127.0.0.1".to_string()),
+                host: Some("127.0.0.1".to_string()),
+                ip: None,
                 port: Some("6379".to_string()),
+                password: None,
+                tls: None,
+                server_addr: None,
+                default_tokens: None,
+                quote_asset: None,
             }
         }
         fn as_env_var_fn(&self) -> impl Fn(&str) -> Result<String, VarError> + '_ {
             |key| match key {
+                "REDIS_DB_HOST" => self.host.clone().ok_or(VarError::NotPresent),
                 "REDIS_DB_IP" => self.ip.clone().ok_or(VarError::NotPresent),
                 "REDIS_DB_PORT" => self.port.clone().ok_or(VarError::NotPresent),
+                "REDIS_DB_PASSWORD" => self.password.clone().ok_or(VarError::NotPresent),
+                "REDIS_DB_TLS" => self.tls.clone().ok_or(VarError::NotPresent),
+                "SERVER_ADDR" => self.server_addr.clone().ok_or(VarError::NotPresent),
+                "DEFAULT_TOKENS" => self.default_tokens.clone().ok_or(VarError::NotPresent),
+                "QUOTE_ASSET" => self.quote_asset.clone().ok_or(VarError::NotPresent),
                 _ => Err(VarError::NotPresent),
             }
         }
@@ -53,15 +123,93 @@ mod tests {
     fn test_load_from_env_success() {
         let env = TestEnvVars::good();
         let config = load_from_env(env.as_env_var_fn());
-        assert_eq!(config.ip, IpAddr::from_str("127.0.0.1").unwrap());
+        assert_eq!(config.host, "127.0.0.1");
         assert_eq!(config.port, 6379u16);
+        assert_eq!(config.password, None);
+        assert!(!config.use_tls);
+        assert_eq!(config.server_addr, SocketAddr::from_str(DEFAULT_SERVER_ADDR).unwrap());
+        assert_eq!(config.default_tokens, DEFAULT_TOKENS);
+        assert_eq!(config.quote_asset, "USDT");
+    }
+
+    #[test]
+    fn test_load_from_env_with_default_tokens() {
+        let mut env = TestEnvVars::good();
+        env.default_tokens = Some("BTC, ETH ,".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert_eq!(config.default_tokens, vec!["BTC", "ETH"]);
+    }
+
+    #[test]
+    fn test_load_from_env_with_quote_asset() {
+        let mut env = TestEnvVars::good();
+        env.quote_asset = Some(" usdc ".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert_eq!(config.quote_asset, "USDC");
+    }
+
+    #[test]
+    fn test_load_from_env_with_server_addr() {
+        let mut env = TestEnvVars::good();
+        env.server_addr = Some("127.0.0.1:3000".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert_eq!(config.server_addr, SocketAddr::from_str("127.0.0.1:3000").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "SERVER_ADDR is invalid")]
+    fn test_load_from_env_invalid_server_addr() {
+        let mut env = TestEnvVars::good();
+        env.server_addr = Some("not_an_addr".to_string());
+        let _ = load_from_env(env.as_env_var_fn());
+    }
+
+    #[test]
+    fn test_load_from_env_with_password() {
+        let mut env = TestEnvVars::good();
+        env.password = Some("s3cret".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert_eq!(config.password.as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn test_load_from_env_with_tls() {
+        let mut env = TestEnvVars::good();
+        env.tls = Some("true".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert!(config.use_tls);
+    }
+
+    #[test]
+    #[should_panic(expected = "REDIS_DB_TLS must be true or false")]
+    fn test_load_from_env_invalid_tls() {
+        let mut env = TestEnvVars::good();
+        env.tls = Some("yes".to_string());
+        let _ = load_from_env(env.as_env_var_fn());
+    }
+
+    #[test]
+    fn test_load_from_env_with_host_name() {
+        let mut env = TestEnvVars::good();
+        env.host = Some("my-redis.example.com".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert_eq!(config.host, "my-redis.example.com");
+    }
+
+    #[test]
+    fn test_load_from_env_falls_back_to_ip() {
+        let mut env = TestEnvVars::good();
+        env.host = None;
+        env.ip = Some("10.0.0.2".to_string());
+        let config = load_from_env(env.as_env_var_fn());
+        assert_eq!(config.host, "10.0.0.2");
     }
 
     #[test]
-    #[should_panic(expected = "REDIS_DB_IP is missing or invalid")]
-    fn test_load_from_env_missing_ip() {
+    #[should_panic(expected = "REDIS_DB_HOST is missing or invalid")]
+    fn test_load_from_env_missing_host() {
         let mut env = TestEnvVars::good();
-        env.ip = None;
+        env.host = None;
         let _ = load_from_env(env.as_env_var_fn());
     }
 
@@ -74,10 +222,10 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "REDIS_DB_IP is missing or invalid")]
-    fn test_load_from_env_invalid_ip() {
+    #[should_panic(expected = "REDIS_DB_HOST is missing or invalid")]
+    fn test_load_from_env_invalid_host() {
         let mut env = TestEnvVars::good();
-        env.ip = Some("not_an_ip".to_string());
+        env.host = Some("not_a_host".to_string());
         let _ = load_from_env(env.as_env_var_fn());
     }
 
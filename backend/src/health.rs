@@ -0,0 +1,93 @@
+use crate::local_db::LocalDb;
+use crate::price_providers::binance_price_provider::binance_api::BinanceAPI;
+use std::time::{Duration, Instant};
+
+/// Outcome of checking one dependency.
+#[derive(Debug)]
+pub struct ComponentHealth {
+    /// The error message when the check failed.
+    pub status: Result<(), String>,
+    /// How long the check took, failed or not.
+    pub latency: Duration,
+}
+
+impl ComponentHealth {
+    fn check<E: std::fmt::Display>(check: impl FnOnce() -> Result<(), E>) -> Self {
+        let started = Instant::now();
+        let status = check().map_err(|err| err.to_string());
+        ComponentHealth { status, latency: started.elapsed() }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status.is_ok()
+    }
+}
+
+/// State of the services prices depend on, see `health_check`.
+#[derive(Debug)]
+pub struct HealthReport {
+    pub redis: ComponentHealth,
+    pub binance: ComponentHealth,
+}
+
+impl HealthReport {
+    /// Whether every component is up, e.g. for a readiness probe.
+    pub fn is_healthy(&self) -> bool {
+        self.redis.is_ok() && self.binance.is_ok()
+    }
+}
+
+/// PINGs Redis and Binance's `/api/v3/ping`, failures are reported rather than returned.
+pub fn health_check(db: &LocalDb, api: &dyn BinanceAPI) -> HealthReport {
+    HealthReport {
+        redis: ComponentHealth::check(|| db.ping()),
+        binance: ComponentHealth::check(|| api.ping()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_db::DEFAULT_CONNECT_TIMEOUT;
+    use serial_test::serial;
+    use crate::price_providers::binance_price_provider::mock_binance_api::MockBinanceAPI;
+
+    /// Db on a port nothing listens on, gives up quickly
+    fn down_db() -> LocalDb {
+        LocalDb::new("127.0.0.1", 1, None, false, Duration::from_millis(200)).unwrap()
+    }
+
+    #[test]
+    fn test_health_check_reports_each_component() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_ping().times(1).returning(|| Ok(()));
+
+        let report = health_check(&down_db(), &mock_api);
+
+        assert!(report.binance.is_ok());
+        assert!(!report.redis.is_ok());
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn test_health_check_reports_binance_failure() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_ping().times(1).returning(|| Err(anyhow::Error::msg("connection refused")));
+
+        let report = health_check(&down_db(), &mock_api);
+
+        assert_eq!(report.binance.status, Err("connection refused".to_string()));
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_health_check_with_everything_up() {
+        let db = LocalDb::new("127.0.0.1", 6379, None, false, DEFAULT_CONNECT_TIMEOUT).unwrap();
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_ping().returning(|| Ok(()));
+
+        assert!(health_check(&db, &mock_api).is_healthy());
+    }
+}
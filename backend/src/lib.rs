@@ -0,0 +1,7 @@
+pub mod env;
+pub mod health;
+pub mod local_db;
+pub mod metrics;
+pub mod price_providers;
+pub mod retry;
+pub mod server;
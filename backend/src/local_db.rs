@@ -1,10 +1,264 @@
-use redis::{Client, Connection, RedisError};
-use std::net::IpAddr;
+use crate::price_providers::{FetchProgress, PriceCache, PricePoint, PriceSeries, PriceStore, Watchlist};
+use crate::retry::{with_backoff, FixedBackoff, RetryPolicy};
+use chrono::{DateTime, Utc};
+use redis::{Client, Connection, ConnectionLike, ErrorKind, RedisError};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use std::net::Ipv6Addr;
+use std::ops::DerefMut;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
 const TOKENS_SET: &str = "tokens_of_interest";
+const PRICES_KEY_PREFIX: &str = "prices:";
+const PRICE_WINDOWS_KEY_PREFIX: &str = "price_windows:";
+const LAST_FETCHED_KEY_PREFIX: &str = "last_fetched:";
+const PRICES_CHANNEL_PREFIX: &str = "prices:";
+
+fn redis_url(host: &str, port: u16, password: Option<&str>, use_tls: bool) -> String {
+    let scheme = if use_tls { "rediss" } else { "redis" };
+    let host = match host.parse::<Ipv6Addr>() {
+        Ok(ip) => format!("[{}]", ip),
+        Err(_) => host.to_string(),
+    };
+    match password {
+        Some(password) => format!("{}://:{}@{}:{}/", scheme, urlencoding::encode(password), host, port),
+        None => format!("{}://{}:{}/", scheme, host, port),
+    }
+}
+
+fn prices_key(symbol: &str) -> String {
+    format!("{}{}", PRICES_KEY_PREFIX, symbol)
+}
+
+fn last_fetched_key(symbol: &str) -> String {
+    format!("{}{}", LAST_FETCHED_KEY_PREFIX, symbol)
+}
+
+fn prices_channel(symbol: &str) -> String {
+    format!("{}{}", PRICES_CHANNEL_PREFIX, symbol)
+}
+
+/// Hash of window start millis to price, for direct lookups of a window.
+fn price_windows_key(symbol: &str) -> String {
+    format!("{}{}", PRICE_WINDOWS_KEY_PREFIX, symbol)
+}
+
+/// Queues the sorted set writes of `series`, replacing entries with the same timestamp.
+fn queue_sorted_set_writes(pipe: &mut redis::Pipeline, symbol: &str, series: &PriceSeries) {
+    let key = prices_key(symbol);
+    for point in series {
+        let score = point.timestamp.timestamp_millis();
+        pipe.cmd("ZREMRANGEBYSCORE").arg(&key).arg(score).arg(score).ignore();
+        pipe.cmd("ZADD").arg(&key).arg(score).arg(price_member(point)).ignore();
+    }
+}
+
+/// Sorted set members must be unique, so the timestamp is kept in the member
+/// alongside the price: two windows with the same price don't collapse into one.
+fn price_member(point: &PricePoint) -> String {
+    format!("{}:{}", point.timestamp.timestamp_millis(), point.price)
+}
+
+fn parse_price_member(member: &str) -> Result<PricePoint, RedisError> {
+    let invalid = || RedisError::from((ErrorKind::TypeError, "Invalid cached price", member.to_string()));
+    let (millis, price) = member.split_once(':').ok_or_else(invalid)?;
+    let timestamp = millis.parse::<i64>().ok()
+        .and_then(DateTime::from_timestamp_millis)
+        .ok_or_else(invalid)?;
+    let price = Decimal::from_str(price).map_err(|_| invalid())?;
+    Ok(PricePoint { timestamp, price })
+}
+
+/// Tokens of interest returned by `LocalDb::read_tokens_or_defaults`.
+#[derive(Debug, PartialEq)]
+pub struct Tokens {
+    pub tokens: Vec<String>,
+    /// Whether the db had no tokens and was seeded with the defaults.
+    pub seeded: bool,
+}
+
+/// Reads the tokens of interest, populating `defaults` when there are none.
+fn read_tokens_or_defaults(con: &mut dyn ConnectionLike, defaults: &[&str]) -> Result<Tokens, RedisError> {
+    let tokens: Vec<String> = redis::cmd("SMEMBERS")
+        .arg(TOKENS_SET)
+        .query(con)?;
+
+    if tokens.is_empty() {
+        if !defaults.is_empty() {
+            redis::cmd("SADD").arg(TOKENS_SET).arg(defaults).query::<()>(con)?;
+        }
+        Ok(Tokens { tokens: defaults.iter().map(|token| token.to_string()).collect(), seeded: true })
+    } else {
+        Ok(Tokens { tokens, seeded: false })
+    }
+}
+
+/// Keys `SCAN` is asked to check per call, small enough not to block the server.
+const SCAN_COUNT: usize = 100;
+
+/// Symbols with a `prices:{symbol}` key, found with `SCAN` so Redis isn't blocked
+/// like with `KEYS`. Sorted, as `SCAN` may return a key more than once.
+fn cached_symbols(con: &mut dyn ConnectionLike) -> Result<Vec<String>, RedisError> {
+    let mut symbols = Vec::new();
+    let mut cursor = 0u64;
+    loop {
+        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH").arg(format!("{}*", PRICES_KEY_PREFIX))
+            .arg("COUNT").arg(SCAN_COUNT)
+            .query(con)?;
+        symbols.extend(keys.into_iter().filter_map(|key| key.strip_prefix(PRICES_KEY_PREFIX).map(str::to_string)));
+        if next_cursor == 0 {
+            break;
+        }
+        cursor = next_cursor;
+    }
+    symbols.sort();
+    symbols.dedup();
+    Ok(symbols)
+}
+
+/// Default time to wait for the Redis server before giving up.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default maximum number of pooled connections.
+pub const DEFAULT_POOL_SIZE: u32 = 8;
+
+/// Times a command is retried on a fresh connection after the connection failed.
+const RECONNECT_ATTEMPTS: u32 = 3;
+/// Wait before reconnecting, gives a restarting server a moment to come back.
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+static RECONNECT_POLICY: LazyLock<RetryPolicy> = LazyLock::new(|| RetryPolicy {
+    max_retries: RECONNECT_ATTEMPTS,
+    backoff: Arc::new(FixedBackoff(RECONNECT_DELAY)),
+});
+
+/// Whether the connection itself failed, so the command may succeed on a new one.
+/// Timeouts aren't retried: the server already had `connect_timeout` to answer.
+fn is_connection_error(err: &RedisError) -> bool {
+    !err.is_timeout() && (err.is_connection_dropped() || err.is_connection_refusal() || err.is_io_error())
+}
+
+/// Runs `command` on a connection from `connect`, reconnecting up to `RECONNECT_ATTEMPTS`
+/// times when the connection fails. Command errors (wrong type, bad reply...) are returned as is.
+fn with_reconnect<C, T>(
+    mut connect: impl FnMut() -> Result<C, RedisError>,
+    mut command: impl FnMut(&mut dyn ConnectionLike) -> Result<T, RedisError>,
+) -> Result<T, RedisError>
+where
+    C: DerefMut,
+    C::Target: ConnectionLike + Sized,
+{
+    with_backoff(&RECONNECT_POLICY, || connect().and_then(|mut con| command(&mut *con)), should_reconnect)
+}
+
+/// Same as `with_reconnect` but only connecting is retried, `command` is sent once.
+/// For commands that mustn't run twice: if the connection drops after the server ran
+/// one, sending it again would repeat it.
+fn with_reconnect_once<C, T>(
+    connect: impl FnMut() -> Result<C, RedisError>,
+    command: impl FnOnce(&mut dyn ConnectionLike) -> Result<T, RedisError>,
+) -> Result<T, RedisError>
+where
+    C: DerefMut,
+    C::Target: ConnectionLike + Sized,
+{
+    let mut con = with_backoff(&RECONNECT_POLICY, connect, should_reconnect)?;
+    command(&mut *con)
+}
+
+fn should_reconnect(err: &RedisError) -> bool {
+    let reconnect = is_connection_error(err);
+    if reconnect {
+        tracing::warn!(error = %err, "Redis connection failed, reconnecting");
+    }
+    reconnect
+}
+
+/// Opens pooled connections with the configured timeouts.
+struct ConnectionManager {
+    client: Client,
+    connect_timeout: Duration,
+}
+
+impl r2d2::ManageConnection for ConnectionManager {
+    type Connection = Connection;
+    type Error = RedisError;
+
+    fn connect(&self) -> Result<Connection, RedisError> {
+        let con = self.client.get_connection_with_timeout(self.connect_timeout)?;
+        // A server that accepts the connection but never answers shouldn't hang us either
+        con.set_read_timeout(Some(self.connect_timeout))?;
+        con.set_write_timeout(Some(self.connect_timeout))?;
+        Ok(con)
+    }
+
+    fn is_valid(&self, con: &mut Connection) -> Result<(), RedisError> {
+        redis::cmd("PING").query(con)
+    }
+
+    fn has_broken(&self, con: &mut Connection) -> bool {
+        !con.is_open()
+    }
+}
+
+type PooledConnection = r2d2::PooledConnection<ConnectionManager>;
+
+/// Keeps the last error the pool got while connecting and when. r2d2 only reports
+/// checkout failures as a timeout, this lets `get_connection` hint at why, e.g. that
+/// connections are refused.
+///
+/// Best effort: r2d2 connects on its own threads for whichever checkout is waiting and
+/// the slot is shared by the whole pool, so with concurrent checkouts the error may come
+/// from an attempt made for another one. It's a hint, not the cause of a given failure.
+#[derive(Debug, Default)]
+struct LastConnectError(Arc<Mutex<Option<(Instant, RedisError)>>>);
+
+impl LastConnectError {
+    /// The error for a checkout started at `since` that failed with `err`: a timeout,
+    /// of the kind of the last connect error the pool got since then if there's one.
+    fn checkout_error(&self, since: Instant, err: r2d2::Error) -> RedisError {
+        let last = self.0.lock().unwrap();
+        let Some((_, connect_err)) = last.as_ref().filter(|(at, _)| *at >= since) else {
+            return RedisError::from(std::io::Error::new(std::io::ErrorKind::TimedOut, err.to_string()));
+        };
+        let message = format!("{}, the pool last failed to connect with: {}", err, connect_err);
+        if connect_err.kind() != ErrorKind::IoError {
+            return RedisError::from((connect_err.kind(), "No connection checked out", message));
+        }
+        let io_kind = if connect_err.is_connection_refusal() {
+            std::io::ErrorKind::ConnectionRefused
+        } else if connect_err.is_connection_dropped() {
+            std::io::ErrorKind::ConnectionReset
+        } else {
+            std::io::ErrorKind::TimedOut
+        };
+        RedisError::from(std::io::Error::new(io_kind, message))
+    }
+}
+
+impl r2d2::HandleError<RedisError> for LastConnectError {
+    fn handle_error(&self, error: RedisError) {
+        *self.0.lock().unwrap() = Some((Instant::now(), error));
+    }
+}
+
+fn build_pool(client: Client, connect_timeout: Duration, max_size: u32, last_connect_error: &LastConnectError) -> r2d2::Pool<ConnectionManager> {
+    r2d2::Pool::builder()
+        .max_size(max_size)
+        // Connect lazily so a db that's down doesn't fail construction
+        .min_idle(Some(0))
+        .connection_timeout(connect_timeout)
+        .error_handler(Box::new(LastConnectError(last_connect_error.0.clone())))
+        .build_unchecked(ConnectionManager { client, connect_timeout })
+}
 
 pub struct LocalDb {
     client: Client,
+    connect_timeout: Duration,
+    pool: r2d2::Pool<ConnectionManager>,
+    last_connect_error: LastConnectError,
 }
 
 impl LocalDb {
@@ -13,21 +267,57 @@ impl LocalDb {
     ///
     /// # Arguments
     ///
-    /// * `ip` - The IP address of the Redis server
+    /// * `host` - Hostname or IP address of the Redis server. With TLS use the hostname
+    ///   the server certificate was issued for, it's what the certificate is verified against
     /// * `port` - The port number of the Redis server
+    /// * `password` - Password for servers requiring `AUTH`, if any
+    /// * `use_tls` - Connect over TLS (`rediss://`), as managed Redis services require
+    /// * `connect_timeout` - How long to wait for the server, see `DEFAULT_CONNECT_TIMEOUT`.
+    ///   An unreachable server makes every call fail with an error for which
+    ///   `RedisError::is_timeout` is true, so callers can tell it apart and retry.
+    ///   A refused or dropped connection is reported as such and reconnected, see `with_reconnect`.
+    ///
+    /// Connections are pooled, up to `DEFAULT_POOL_SIZE` unless changed with `with_pool_size`,
+    /// and only opened when first needed.
     ///
     /// # Returns
     ///
     /// * `Ok(LocalDb)` if the connection is successful.
     /// * `Err(RedisError)` if there is an error connecting to Redis.
-    pub fn new(ip: IpAddr, port: u16) -> Result<Self, RedisError> {
-        let url = format!("redis://{}:{}/", ip, port);
-        let client = Client::open(url)?;
-        Ok(LocalDb { client })
+    pub fn new(host: &str, port: u16, password: Option<&str>, use_tls: bool, connect_timeout: Duration) -> Result<Self, RedisError> {
+        let client = Client::open(redis_url(host, port, password, use_tls))?;
+        let last_connect_error = LastConnectError::default();
+        let pool = build_pool(client.clone(), connect_timeout, DEFAULT_POOL_SIZE, &last_connect_error);
+        Ok(LocalDb { client, connect_timeout, pool, last_connect_error })
+    }
+
+    /// Allows up to `max_size` connections to be open at once.
+    pub fn with_pool_size(mut self, max_size: u32) -> Self {
+        self.pool = build_pool(self.client.clone(), self.connect_timeout, max_size, &self.last_connect_error);
+        self
     }
 
-    fn get_connection(&self) -> Result<Connection, RedisError> {
-        self.client.get_connection()
+    /// Checks out a pooled connection. On failure the error is shaped after the last
+    /// one the pool got connecting during the checkout, see `LastConnectError`, or is a
+    /// timeout (every connection busy).
+    fn get_connection(&self) -> Result<PooledConnection, RedisError> {
+        let started = Instant::now();
+        self.pool.get().map_err(|err| self.last_connect_error.checkout_error(started, err))
+    }
+
+    /// Runs `command` on a pooled connection, see `with_reconnect`.
+    fn with_connection<T>(&self, command: impl FnMut(&mut dyn ConnectionLike) -> Result<T, RedisError>) -> Result<T, RedisError> {
+        with_reconnect(|| self.get_connection(), command)
+    }
+
+    /// Checks the server answers.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The server answered `PONG`.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn ping(&self) -> Result<(), RedisError> {
+        self.with_connection(|con| redis::cmd("PING").query(con))
     }
 
     /// Reads tokens of interest from db. 
@@ -39,22 +329,764 @@ impl LocalDb {
     ///
     /// # Returns
     ///
-    /// * `Ok(Vec<String>)` - Tokens of interest from the db.
+    /// * `Ok(Tokens)` - Tokens of interest from the db, or the defaults with `seeded` set.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn read_tokens_or_defaults(&self, defaults: &[&str]) -> Result<Tokens, RedisError> {
+        self.with_connection(|con| read_tokens_or_defaults(con, defaults))
+    }
+
+    /// Adds a token to the tokens of interest.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Token to add.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - The token was added.
+    /// * `Ok(false)` - The token was already present.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn add_token(&self, token: &str) -> Result<bool, RedisError> {
+        self.with_connection(|con| redis::cmd("SADD").arg(TOKENS_SET).arg(token).query(con))
+    }
+
+    /// Removes a token from the tokens of interest.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Token to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - The token was removed.
+    /// * `Ok(false)` - The token wasn't present.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn remove_token(&self, token: &str) -> Result<bool, RedisError> {
+        self.with_connection(|con| redis::cmd("SREM").arg(TOKENS_SET).arg(token).query(con))
+    }
+
+    /// Checks whether a token is among the tokens of interest.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Token to look for.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the token is present.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn contains_token(&self, token: &str) -> Result<bool, RedisError> {
+        self.with_connection(|con| redis::cmd("SISMEMBER").arg(TOKENS_SET).arg(token).query(con))
+    }
+
+    /// Stores the series in the `prices:{symbol}` sorted set scored by timestamp millis.
+    /// Points already cached for the same timestamps are replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol the prices belong to.
+    /// * `series` - Prices to cache.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The whole series was stored.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn cache_prices(&self, symbol: &str, series: &PriceSeries) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        queue_sorted_set_writes(&mut pipe, symbol, series);
+        self.with_connection(|con| pipe.query(con))
+    }
+
+    /// Lists the symbols with cached prices, see `cache_prices`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - The symbols, sorted.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn cached_symbols(&self) -> Result<Vec<String>, RedisError> {
+        self.with_connection(cached_symbols)
+    }
+
+    /// Removes the cached prices of a symbol, from both the sorted set and the window hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol whose prices are evicted.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - Prices were removed.
+    /// * `Ok(false)` - Nothing was cached for the symbol.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn evict_prices(&self, symbol: &str) -> Result<bool, RedisError> {
+        let removed: u32 = self.with_connection(|con| {
+            redis::cmd("DEL").arg(prices_key(symbol)).arg(price_windows_key(symbol)).query(con)
+        })?;
+        Ok(removed > 0)
+    }
+
+    /// Stores prices in both the per-window hash and the range-queryable sorted set,
+    /// in a single atomic pipeline so the two never disagree.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol the prices belong to.
+    /// * `series` - Prices to store.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The whole series was stored in both.
+    /// * `Err(RedisError)` - Any db error, nothing is stored.
+    pub fn store_prices(&self, symbol: &str, series: &PriceSeries) -> Result<(), RedisError> {
+        if series.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let windows_key = price_windows_key(symbol);
+        for point in series {
+            pipe.cmd("HSET").arg(&windows_key).arg(point.timestamp.timestamp_millis()).arg(point.price.to_string()).ignore();
+        }
+        queue_sorted_set_writes(&mut pipe, symbol, series);
+        self.with_connection(|con| pipe.query(con))
+    }
+
+    /// Records where the last successful fetch of `symbol` ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol that was fetched.
+    /// * `timestamp` - End of the fetched range, stored as millis.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The marker was stored.
+    /// * `Err(RedisError)` - Any db error.
+    pub fn set_last_fetched(&self, symbol: &str, timestamp: &DateTime<Utc>) -> Result<(), RedisError> {
+        self.with_connection(|con| redis::cmd("SET").arg(last_fetched_key(symbol)).arg(timestamp.timestamp_millis()).query(con))
+    }
+
+    /// Reads where the last successful fetch of `symbol` ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol to look up.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DateTime<Utc>))` - The stored marker.
+    /// * `Ok(None)` - The symbol was never fetched.
+    /// * `Err(RedisError)` - Any db error or an unparseable marker.
+    pub fn get_last_fetched(&self, symbol: &str) -> Result<Option<DateTime<Utc>>, RedisError> {
+        let millis: Option<i64> = self.with_connection(|con| redis::cmd("GET").arg(last_fetched_key(symbol)).query(con))?;
+        millis.map(|millis| DateTime::from_timestamp_millis(millis)
+                .ok_or_else(|| RedisError::from((ErrorKind::TypeError, "Invalid last fetched timestamp", millis.to_string()))))
+            .transpose()
+    }
+
+    /// Reads the price stored for the window starting at `window_start`.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol to read the price for.
+    /// * `window_start` - Start of the window.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Decimal))` - The stored price.
+    /// * `Ok(None)` - Nothing stored for that window.
+    /// * `Err(RedisError)` - Any db error or an unparseable price.
+    pub fn read_window_price(&self, symbol: &str, window_start: &DateTime<Utc>) -> Result<Option<Decimal>, RedisError> {
+        let price: Option<String> = self.with_connection(|con| redis::cmd("HGET")
+            .arg(price_windows_key(symbol))
+            .arg(window_start.timestamp_millis())
+            .query(con))?;
+        price.map(|price| Decimal::from_str(&price)
+                .map_err(|_| RedisError::from((ErrorKind::TypeError, "Invalid window price", price.clone()))))
+            .transpose()
+    }
+
+    /// Publishes `point` as JSON to the `prices:{symbol}` channel.
+    ///
+    /// `PUBLISH` is sent at most once, a connection dropping mid command isn't retried
+    /// as the server may have delivered the price already. Subscribers get it at most once.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol the price belongs to.
+    /// * `point` - Price to publish.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The price was published, whether or not anyone is subscribed.
     /// * `Err(RedisError)` - Any db error.
-    pub fn read_tokens_or_defaults(&self, defaults: &[&str]) -> Result<Vec<String>, RedisError> {
-        let mut con = self.get_connection()?;
-        let tokens: Vec<String> = redis::cmd("SMEMBERS")
-            .arg(TOKENS_SET)
-            .query(&mut con)?;
+    pub fn publish_price(&self, symbol: &str, point: &PricePoint) -> Result<(), RedisError> {
+        let payload = serde_json::to_string(point)
+            .map_err(|err| RedisError::from((ErrorKind::TypeError, "Price can't be serialized", err.to_string())))?;
+        with_reconnect_once(|| self.get_connection(), |con| redis::cmd("PUBLISH").arg(prices_channel(symbol)).arg(&payload).query(con))
+    }
+
+    /// Subscribes to the prices published for `symbol` from now on.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol to receive prices for.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(PriceSubscription)` - Iterator over the published prices, on a dedicated connection.
+    /// * `Err(RedisError)` - The connection or the subscription failed.
+    pub fn subscribe_prices(&self, symbol: &str) -> Result<PriceSubscription, RedisError> {
+        let mut con = self.client.get_connection_with_timeout(self.connect_timeout)?;
+        redis::cmd("SUBSCRIBE").arg(prices_channel(symbol)).query::<redis::Value>(&mut con)?;
+        Ok(PriceSubscription { con })
+    }
+
+    /// Reads the cached prices of `symbol` with timestamps in `[start_time, end_time]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - Symbol to read prices for.
+    /// * `start_time` - Start of the range, inclusive.
+    /// * `end_time` - End of the range, inclusive.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(PriceSeries)` - Cached prices in ascending timestamp order, empty if none.
+    /// * `Err(RedisError)` - Any db error or an unparseable cached entry.
+    pub fn read_cached_prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<PriceSeries, RedisError> {
+        let members: Vec<String> = self.with_connection(|con| redis::cmd("ZRANGEBYSCORE")
+            .arg(prices_key(symbol))
+            .arg(start_time.timestamp_millis())
+            .arg(end_time.timestamp_millis())
+            .query(con))?;
+        members.iter().map(|member| parse_price_member(member)).collect()
+    }
+}
+
+/// Prices published to a `prices:{symbol}` channel, see `LocalDb::subscribe_prices`.
+///
+/// Holds its own connection, blocking on `next` until a price is published. The
+/// iterator only ends if the connection does, unparseable messages are yielded as errors.
+pub struct PriceSubscription {
+    con: Connection,
+}
 
-        if tokens.is_empty() {
-            println!("No tokens of interest found in db, populating with defaults");
-            for token in defaults {
-                redis::cmd("SADD").arg(TOKENS_SET).arg(token).execute(&mut con);
+impl Iterator for PriceSubscription {
+    type Item = Result<PricePoint, RedisError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = match self.con.recv_response() {
+                Ok(value) => value,
+                Err(err) if err.is_connection_dropped() => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            // Subscription confirmations and other replies aren't messages
+            if let Some(msg) = redis::Msg::from_value(&value) {
+                return Some(msg.get_payload::<String>().and_then(|payload| {
+                    serde_json::from_str(&payload)
+                        .map_err(|_| RedisError::from((ErrorKind::TypeError, "Invalid published price", payload)))
+                }));
             }
-            Ok(defaults.iter().map(|token| token.to_string()).collect())
-        } else {
-            Ok(tokens)
         }
     }
-} 
\ No newline at end of file
+}
+
+impl PriceCache for LocalDb {
+    fn read_cached_prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        Ok(LocalDb::read_cached_prices(self, symbol, start_time, end_time)?)
+    }
+
+    fn cache_prices(&self, symbol: &str, series: &PriceSeries) -> anyhow::Result<()> {
+        Ok(LocalDb::cache_prices(self, symbol, series)?)
+    }
+}
+
+impl FetchProgress for LocalDb {
+    fn get_last_fetched(&self, symbol: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
+        Ok(LocalDb::get_last_fetched(self, symbol)?)
+    }
+
+    fn set_last_fetched(&self, symbol: &str, timestamp: &DateTime<Utc>) -> anyhow::Result<()> {
+        Ok(LocalDb::set_last_fetched(self, symbol, timestamp)?)
+    }
+}
+
+impl Watchlist for LocalDb {
+    fn tokens_or_defaults(&self, defaults: &[&str]) -> anyhow::Result<Vec<String>> {
+        Ok(LocalDb::read_tokens_or_defaults(self, defaults)?.tokens)
+    }
+}
+
+impl PriceStore for LocalDb {
+    fn store_prices(&self, symbol: &str, series: &PriceSeries) -> anyhow::Result<()> {
+        Ok(LocalDb::store_prices(self, symbol, series)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use redis::Value;
+    use rust_decimal_macros::dec;
+    use serial_test::serial;
+
+    fn test_db() -> LocalDb {
+        LocalDb::new("127.0.0.1", 6379, None, false, DEFAULT_CONNECT_TIMEOUT).unwrap()
+    }
+
+    fn clear_key(db: &LocalDb, key: &str) {
+        let mut con = db.get_connection().unwrap();
+        redis::cmd("DEL").arg(key).execute(&mut *con);
+    }
+
+    fn three_point_series() -> PriceSeries {
+        let start = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        [dec!(1.5), dec!(2.25), dec!(1.5)].iter().enumerate()
+            .map(|(i, price)| PricePoint { timestamp: start + Duration::minutes(i as i64), price: *price })
+            .collect()
+    }
+
+    /// Answers commands with `replies` in order and records the commands it got.
+    struct FakeConnection {
+        replies: std::collections::VecDeque<redis::RedisResult<Value>>,
+        commands: Vec<String>,
+    }
+
+    impl FakeConnection {
+        fn new(replies: Vec<redis::RedisResult<Value>>) -> Self {
+            FakeConnection { replies: replies.into(), commands: Vec::new() }
+        }
+
+        /// Commands received that start with `name`.
+        fn count(&self, name: &str) -> usize {
+            self.commands.iter().filter(|command| command.starts_with(name)).count()
+        }
+    }
+
+    impl ConnectionLike for FakeConnection {
+        fn req_packed_command(&mut self, cmd: &[u8]) -> redis::RedisResult<Value> {
+            // Keep the bulk strings of the RESP array, e.g. "SADD tokens_of_interest UNI"
+            let args: Vec<&str> = std::str::from_utf8(cmd).unwrap().split("\r\n")
+                .skip(1)
+                .filter(|part| !part.is_empty() && !part.starts_with('$'))
+                .collect();
+            self.commands.push(args.join(" "));
+            self.replies.pop_front().unwrap_or(Ok(Value::Nil))
+        }
+
+        fn req_packed_commands(&mut self, _cmd: &[u8], _offset: usize, _count: usize) -> redis::RedisResult<Vec<Value>> {
+            unimplemented!("pipelines aren't used by these tests")
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+
+        fn check_connection(&mut self) -> bool {
+            true
+        }
+
+        fn is_open(&self) -> bool {
+            true
+        }
+    }
+
+    fn tokens_reply(tokens: &[&str]) -> Value {
+        Value::Bulk(tokens.iter().map(|token| Value::Data(token.as_bytes().to_vec())).collect())
+    }
+
+    #[test]
+    fn test_with_reconnect_retries_after_connection_failure() {
+        let mut connects = 0;
+        let connect = || {
+            connects += 1;
+            if connects == 1 {
+                Err(RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)))
+            } else {
+                Ok(Box::new(FakeConnection::new(vec![Ok(tokens_reply(&["BTC", "ETH"]))])))
+            }
+        };
+
+        let tokens = with_reconnect(connect, |con| read_tokens_or_defaults(con, &["UNI"])).unwrap();
+
+        assert_eq!(tokens.tokens, vec!["BTC", "ETH"]);
+        assert!(!tokens.seeded);
+        assert_eq!(connects, 2);
+    }
+
+    #[test]
+    fn test_read_tokens_or_defaults_seeds_with_a_single_sadd() {
+        let mut con = FakeConnection::new(vec![Ok(tokens_reply(&[])), Ok(Value::Int(3))]);
+
+        let tokens = read_tokens_or_defaults(&mut con, &["UNI", "ZRX", "ETH"]).unwrap();
+
+        assert_eq!(tokens, Tokens { tokens: vec!["UNI".to_string(), "ZRX".to_string(), "ETH".to_string()], seeded: true });
+        assert_eq!(con.count("SADD"), 1);
+        assert_eq!(con.commands.last().unwrap(), &format!("SADD {} UNI ZRX ETH", TOKENS_SET));
+    }
+
+    #[test]
+    fn test_read_tokens_or_defaults_does_not_seed_existing_tokens() {
+        let mut con = FakeConnection::new(vec![Ok(tokens_reply(&["BTC"]))]);
+
+        let tokens = read_tokens_or_defaults(&mut con, &["UNI"]).unwrap();
+
+        assert_eq!(tokens, Tokens { tokens: vec!["BTC".to_string()], seeded: false });
+        assert_eq!(con.count("SADD"), 0);
+    }
+
+    #[test]
+    fn test_cached_symbols_follows_scan_cursor() {
+        let page = |cursor: &str, keys: &[&str]| Value::Bulk(vec![
+            Value::Data(cursor.as_bytes().to_vec()),
+            tokens_reply(keys),
+        ]);
+        let mut con = FakeConnection::new(vec![
+            Ok(page("17", &["prices:ETHUSDT", "prices:BTCUSDC"])),
+            Ok(page("0", &["prices:BTCUSDC"])),
+        ]);
+
+        assert_eq!(cached_symbols(&mut con).unwrap(), vec!["BTCUSDC", "ETHUSDT"]);
+        assert_eq!(con.count("SCAN"), 2);
+        assert_eq!(con.commands.last().unwrap(), "SCAN 17 MATCH prices:* COUNT 100");
+    }
+
+    #[test]
+    fn test_read_tokens_or_defaults_returns_seeding_errors() {
+        let failure = RedisError::from((ErrorKind::ResponseError, "OOM command not allowed"));
+        let mut con = FakeConnection::new(vec![Ok(tokens_reply(&[])), Err(failure)]);
+
+        assert!(read_tokens_or_defaults(&mut con, &["UNI", "ZRX"]).is_err());
+    }
+
+    #[test]
+    fn test_with_reconnect_does_not_retry_command_errors() {
+        let mut connects = 0;
+        let connect = || {
+            connects += 1;
+            Ok(Box::new(FakeConnection::new(vec![Ok(Value::Okay)])))
+        };
+
+        let result = with_reconnect(connect, |con| redis::cmd("GET").arg("key").query::<i64>(con));
+
+        assert!(result.is_err());
+        assert_eq!(connects, 1);
+    }
+
+    #[test]
+    fn test_with_reconnect_once_does_not_resend_after_connection_drop() {
+        let mut connects = 0;
+        let connect = || {
+            connects += 1;
+            if connects == 1 {
+                Err(RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)))
+            } else {
+                let dropped = RedisError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+                Ok(Box::new(FakeConnection::new(vec![Err(dropped)])))
+            }
+        };
+        let mut sent = 0;
+
+        let result = with_reconnect_once(connect, |con| {
+            sent += 1;
+            redis::cmd("PUBLISH").arg("prices:BTCUSDC").arg("{}").query::<i64>(con)
+        });
+
+        assert!(result.unwrap_err().is_connection_dropped());
+        assert_eq!(connects, 2);
+        assert_eq!(sent, 1);
+    }
+
+    /// A port nothing listens on, connections to it are refused.
+    fn closed_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    /// Answers every command on `listener` with `+PONG`, enough for `ping`.
+    fn serve_pong(listener: std::net::TcpListener) {
+        use std::io::{Read, Write};
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 1024];
+        while let Ok(read) = stream.read(&mut buf) {
+            if read == 0 {
+                break;
+            }
+            let commands = buf[..read].iter().filter(|byte| **byte == b'*').count();
+            stream.write_all(&b"+PONG\r\n".repeat(commands)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_refused_connection_is_reported_as_refused() {
+        let db = LocalDb::new("127.0.0.1", closed_port(), None, false, std::time::Duration::from_millis(100)).unwrap();
+
+        let err = db.ping().unwrap_err();
+
+        assert!(err.is_connection_refusal(), "{}", err);
+        assert!(!err.is_timeout(), "{}", err);
+    }
+
+    #[test]
+    fn test_concurrent_refused_checkouts_are_all_reported_as_refused() {
+        let db = LocalDb::new("127.0.0.1", closed_port(), None, false, std::time::Duration::from_millis(100)).unwrap();
+
+        std::thread::scope(|scope| {
+            let checkouts: Vec<_> = (0..4).map(|_| scope.spawn(|| db.get_connection().err().unwrap())).collect();
+            for checkout in checkouts {
+                let err = checkout.join().unwrap();
+                assert!(err.is_connection_refusal(), "{}", err);
+            }
+        });
+    }
+
+    #[test]
+    fn test_reconnects_when_the_server_comes_back() {
+        let port = closed_port();
+        let db = LocalDb::new("127.0.0.1", port, None, false, std::time::Duration::from_millis(300)).unwrap();
+        let server = std::thread::spawn(move || {
+            // Comes up while the first checkout has already failed
+            std::thread::sleep(std::time::Duration::from_millis(350));
+            serve_pong(std::net::TcpListener::bind(("127.0.0.1", port)).unwrap());
+        });
+
+        db.ping().unwrap();
+        drop(db);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_unresponsive_server_times_out() {
+        // Connections are accepted through the backlog but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let timeout = std::time::Duration::from_millis(500);
+        let db = LocalDb::new("127.0.0.1", port, None, false, timeout).unwrap();
+
+        let started = std::time::Instant::now();
+        let err = db.contains_token(TEST_TOKEN).unwrap_err();
+
+        assert!(err.is_timeout(), "{}", err);
+        assert!(started.elapsed() < timeout * 4);
+        drop(listener);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_concurrent_reads_share_the_pool() {
+        let db = test_db().with_pool_size(4);
+        db.add_token(TEST_TOKEN).unwrap();
+
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..16)
+                .map(|_| scope.spawn(|| (0..10).map(|_| db.contains_token(TEST_TOKEN)).collect::<Vec<_>>()))
+                .collect();
+            for reader in readers {
+                for result in reader.join().unwrap() {
+                    assert!(result.unwrap());
+                }
+            }
+        });
+        db.remove_token(TEST_TOKEN).unwrap();
+    }
+
+    #[test]
+    fn test_redis_url_without_password() {
+        let url = redis_url("127.0.0.1", 6379, None, false);
+        assert_eq!(url, "redis://127.0.0.1:6379/");
+    }
+
+    #[test]
+    fn test_redis_url_escapes_password() {
+        let url = redis_url("127.0.0.1", 6379, Some("p@ss:w/rd#?"), false);
+        assert_eq!(url, "redis://:p%40ss%3Aw%2Frd%23%3F@127.0.0.1:6379/");
+        assert!(Client::open(url).is_ok());
+    }
+
+    #[test]
+    fn test_redis_url_brackets_ipv6() {
+        let url = redis_url("::1", 6379, None, false);
+        assert_eq!(url, "redis://[::1]:6379/");
+    }
+
+    #[test]
+    fn test_redis_url_uses_rediss_scheme_with_tls() {
+        let url = redis_url("my-redis.example.com", 6380, Some("s3cret"), true);
+        assert_eq!(url, "rediss://:s3cret@my-redis.example.com:6380/");
+        assert!(Client::open(url).is_ok());
+    }
+
+    #[test]
+    fn test_price_member_round_trip() {
+        let point = &three_point_series()[1];
+        let parsed = parse_price_member(&price_member(point)).unwrap();
+        assert_eq!(parsed.timestamp, point.timestamp);
+        assert_eq!(parsed.price, point.price);
+        assert!(parse_price_member("not a member").is_err());
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_cache_prices_round_trip() {
+        let db = test_db();
+        clear_key(&db, &prices_key("TESTCACHE"));
+        let series = three_point_series();
+
+        db.cache_prices("TESTCACHE", &series).unwrap();
+        let cached = db.read_cached_prices("TESTCACHE", &series[0].timestamp, &series[2].timestamp).unwrap();
+
+        assert_eq!(cached.len(), 3);
+        for (cached, original) in cached.iter().zip(series.iter()) {
+            assert_eq!(cached.timestamp, original.timestamp);
+            assert_eq!(cached.price, original.price);
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_cached_symbols_and_evict_prices() {
+        let db = test_db();
+        for symbol in ["TESTCACHEA", "TESTCACHEB"] {
+            clear_key(&db, &prices_key(symbol));
+            db.cache_prices(symbol, &three_point_series()).unwrap();
+        }
+
+        let symbols = db.cached_symbols().unwrap();
+        assert!(symbols.contains(&"TESTCACHEA".to_string()));
+        assert!(symbols.contains(&"TESTCACHEB".to_string()));
+
+        assert!(db.evict_prices("TESTCACHEA").unwrap());
+        assert!(!db.evict_prices("TESTCACHEA").unwrap());
+        let symbols = db.cached_symbols().unwrap();
+        assert!(!symbols.contains(&"TESTCACHEA".to_string()));
+        assert!(symbols.contains(&"TESTCACHEB".to_string()));
+        db.evict_prices("TESTCACHEB").unwrap();
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_last_fetched_round_trip() {
+        let db = test_db();
+        clear_key(&db, &last_fetched_key("TESTCACHE"));
+        let timestamp = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap() + Duration::milliseconds(123);
+
+        assert_eq!(db.get_last_fetched("TESTCACHE").unwrap(), None);
+        db.set_last_fetched("TESTCACHE", &timestamp).unwrap();
+        assert_eq!(db.get_last_fetched("TESTCACHE").unwrap(), Some(timestamp));
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_store_prices_writes_hash_and_sorted_set() {
+        let db = test_db();
+        clear_key(&db, &prices_key("TESTCACHE"));
+        clear_key(&db, &price_windows_key("TESTCACHE"));
+        let series = three_point_series();
+
+        db.store_prices("TESTCACHE", &series).unwrap();
+
+        for point in &series {
+            assert_eq!(db.read_window_price("TESTCACHE", &point.timestamp).unwrap(), Some(point.price));
+        }
+        let cached = db.read_cached_prices("TESTCACHE", &series[0].timestamp, &series[2].timestamp).unwrap();
+        assert_eq!(cached.len(), 3);
+        assert_eq!(db.read_window_price("TESTCACHE", &(series[2].timestamp + Duration::minutes(1))).unwrap(), None);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_read_cached_prices_sub_range() {
+        let db = test_db();
+        clear_key(&db, &prices_key("TESTCACHE"));
+        let series = three_point_series();
+
+        db.cache_prices("TESTCACHE", &series).unwrap();
+        let cached = db.read_cached_prices("TESTCACHE", &series[1].timestamp, &series[2].timestamp).unwrap();
+
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached[0].timestamp, series[1].timestamp);
+        assert_eq!(cached[0].price, dec!(2.25));
+        assert_eq!(cached[1].timestamp, series[2].timestamp);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_cache_prices_replaces_existing_timestamp() {
+        let db = test_db();
+        clear_key(&db, &prices_key("TESTCACHE"));
+        let mut series = three_point_series();
+
+        db.cache_prices("TESTCACHE", &series).unwrap();
+        series[0].price = dec!(9);
+        db.cache_prices("TESTCACHE", &series[..1].to_vec()).unwrap();
+        let cached = db.read_cached_prices("TESTCACHE", &series[0].timestamp, &series[2].timestamp).unwrap();
+
+        assert_eq!(cached.len(), 3);
+        assert_eq!(cached[0].price, dec!(9));
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_published_price_is_received_by_subscription() {
+        let db = test_db();
+        let mut subscription = db.subscribe_prices("TESTPUBSUB").unwrap();
+        let point = three_point_series()[1].clone();
+
+        db.publish_price("TESTPUBSUB", &point).unwrap();
+
+        assert_eq!(subscription.next().unwrap().unwrap(), point);
+    }
+
+    const TEST_TOKEN: &str = "TESTTOKEN";
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_add_token_then_contains() {
+        let db = test_db();
+        db.remove_token(TEST_TOKEN).unwrap();
+
+        assert!(!db.contains_token(TEST_TOKEN).unwrap());
+        assert!(db.add_token(TEST_TOKEN).unwrap());
+        assert!(db.contains_token(TEST_TOKEN).unwrap());
+
+        db.remove_token(TEST_TOKEN).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_add_duplicate_token_returns_false() {
+        let db = test_db();
+        db.remove_token(TEST_TOKEN).unwrap();
+
+        assert!(db.add_token(TEST_TOKEN).unwrap());
+        assert!(!db.add_token(TEST_TOKEN).unwrap());
+
+        db.remove_token(TEST_TOKEN).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a running Redis (docker compose up redis)"]
+    fn test_remove_missing_token_returns_false() {
+        let db = test_db();
+        db.remove_token(TEST_TOKEN).unwrap();
+
+        assert!(!db.remove_token(TEST_TOKEN).unwrap());
+        db.add_token(TEST_TOKEN).unwrap();
+        assert!(db.remove_token(TEST_TOKEN).unwrap());
+        assert!(!db.contains_token(TEST_TOKEN).unwrap());
+    }
+}
@@ -1,16 +1,40 @@
-mod env;
-mod local_db;
-mod price_providers;
-
-use local_db::LocalDb;
-
-const DEFAULT_TOKENS: [&str; 2] = ["UNI", "ZRX"];
+use backend::{env, health};
+use backend::metrics::MetricsExporter;
+use backend::local_db::{LocalDb, DEFAULT_CONNECT_TIMEOUT};
+use backend::price_providers::binance_price_provider::binance_api::BinanceHttpClient;
+use backend::price_providers::BinancePriceProvider;
+use backend::server;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
 
 fn main() {
+    // RUST_LOG overrides the level, e.g. RUST_LOG=backend=debug
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+
     let env_config = env::load_from_env(|key| std::env::var(key));
     
-    let local_db = LocalDb::new(env_config.ip, env_config.port).expect("Failed to connect to db");
+    let local_db = LocalDb::new(&env_config.host, env_config.port, env_config.password.as_deref(), env_config.use_tls, DEFAULT_CONNECT_TIMEOUT).expect("Failed to connect to db");
+
+    let default_tokens: Vec<&str> = env_config.default_tokens.iter().map(String::as_str).collect();
+    let tokens = local_db.read_tokens_or_defaults(&default_tokens).expect("Failed to read tokens");
+    if tokens.seeded {
+        tracing::info!("No tokens of interest found in db, populated with defaults");
+    }
+    tracing::info!("Tokens: {:?}", tokens.tokens);
+
+    let binance_api = Arc::new(BinanceHttpClient::new());
+    let report = health::health_check(&local_db, binance_api.as_ref());
+    if report.is_healthy() {
+        tracing::info!("Health check passed: {:?}", report);
+    } else {
+        tracing::warn!("Health check failed: {:?}", report);
+    }
 
-    let tokens = local_db.read_tokens_or_defaults(&DEFAULT_TOKENS).expect("Failed to read tokens");
-    println!("Tokens: {:?}", tokens);
+    let exporter = MetricsExporter::new(binance_api.clone(), Arc::new(local_db)).expect("Failed to register metrics");
+    let provider = Arc::new(BinancePriceProvider::new(Box::new(binance_api)).with_quote_asset(&env_config.quote_asset));
+    let app = server::router(provider).merge(server::metrics_router(Arc::new(exporter)));
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start async runtime");
+    runtime.block_on(server::serve(env_config.server_addr, app)).expect("Server failed");
 }
@@ -0,0 +1,66 @@
+use crate::local_db::LocalDb;
+use crate::price_providers::binance_price_provider::binance_api::BinanceHttpClient;
+use prometheus::{Encoder, IntCounter, IntGauge, Registry, TextEncoder};
+use std::sync::{Arc, Mutex};
+
+/// Prometheus view of the `BinanceHttpClient` counters and of Redis health.
+///
+/// The metrics are registered once, on creation, and brought up to date from
+/// their sources on every `render`.
+pub struct MetricsExporter {
+    registry: Registry,
+    requests_total: IntCounter,
+    requests_failed: IntCounter,
+    retries_total: IntCounter,
+    last_used_weight: IntGauge,
+    redis_up: IntGauge,
+    client: Arc<BinanceHttpClient>,
+    db: Arc<LocalDb>,
+    /// Keeps concurrent scrapes from adding the same counter increase twice.
+    update_lock: Mutex<()>,
+}
+
+impl MetricsExporter {
+    pub fn new(client: Arc<BinanceHttpClient>, db: Arc<LocalDb>) -> prometheus::Result<Self> {
+        let registry = Registry::new();
+        let requests_total = IntCounter::new("binance_requests_total", "Requests made to Binance, retries not included")?;
+        let requests_failed = IntCounter::new("binance_requests_failed", "Binance requests that failed after their retries")?;
+        let retries_total = IntCounter::new("binance_retries_total", "Binance request attempts beyond the first one")?;
+        let last_used_weight = IntGauge::new("binance_last_used_weight", "Last X-MBX-USED-WEIGHT-1M reported by Binance, 0 until one is")?;
+        let redis_up = IntGauge::new("redis_up", "1 if Redis answered PING, else 0")?;
+        registry.register(Box::new(requests_total.clone()))?;
+        registry.register(Box::new(requests_failed.clone()))?;
+        registry.register(Box::new(retries_total.clone()))?;
+        registry.register(Box::new(last_used_weight.clone()))?;
+        registry.register(Box::new(redis_up.clone()))?;
+        Ok(MetricsExporter {
+            registry, requests_total, requests_failed, retries_total, last_used_weight, redis_up, client, db,
+            update_lock: Mutex::new(()),
+        })
+    }
+
+    /// Updates the metrics, PINGing Redis, and renders them in the text exposition format.
+    pub fn render(&self) -> anyhow::Result<String> {
+        self.update();
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    fn update(&self) {
+        let _guard = self.update_lock.lock().unwrap();
+        let snapshot = self.client.metrics();
+        // The client only counts up, catch the counters up with it
+        for (counter, total) in [
+            (&self.requests_total, snapshot.requests_total),
+            (&self.requests_failed, snapshot.requests_failed),
+            (&self.retries_total, snapshot.retries_total),
+        ] {
+            counter.inc_by(total.saturating_sub(counter.get()));
+        }
+        if let Some(used_weight) = self.client.last_used_weight() {
+            self.last_used_weight.set(used_weight.into());
+        }
+        self.redis_up.set(self.db.ping().is_ok().into());
+    }
+}
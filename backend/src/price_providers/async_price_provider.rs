@@ -0,0 +1,123 @@
+use super::binance_price_provider::async_binance_api::AsyncBinanceAPI;
+use super::series::Aggregation;
+use super::{time_windows, window_price, PriceError, PricePoint, PriceSeries, SchemaMode};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+
+/// Non-blocking counterpart of `BinancePriceProvider`, windows are awaited one after another.
+pub struct AsyncBinancePriceProvider<A: AsyncBinanceAPI> {
+    binance_api: A,
+}
+
+impl<A: AsyncBinanceAPI> AsyncBinancePriceProvider<A> {
+    const TIME_WINDOW: Duration = Duration::minutes(1);
+
+    pub fn new(binance_api: A) -> Self {
+        AsyncBinancePriceProvider { binance_api }
+    }
+
+    async fn fetch_avg_price_for_window(&self, symbol: &str, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>) -> anyhow::Result<Option<Decimal>> {
+        let api_response = self.binance_api.agg_trades(
+            symbol,
+            None,
+            Some( window_start.timestamp_millis() ),
+            Some( window_end.timestamp_millis() ),
+            None).await?;
+        Ok(window_price(symbol, &api_response, SchemaMode::default(), Aggregation::Mean)?)
+    }
+
+    pub async fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        PriceError::check_range(start_time, end_time)?;
+        let mut prices = Vec::new();
+        for (window_start, window_end) in time_windows(start_time, end_time, Self::TIME_WINDOW) {
+            let maybe_price = self.fetch_avg_price_for_window(symbol, &window_start, &window_end).await?;
+            if let Some(avg_price) = maybe_price {
+                prices.push(PricePoint { timestamp: window_start, price: avg_price });
+            }
+        }
+        Ok(prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+    use rust_decimal_macros::dec;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Answers each window by its start time and records the requested windows
+    struct StubAsyncBinanceAPI {
+        responses: HashMap<i64, &'static str>,
+        requested: Mutex<Vec<(i64, i64)>>,
+    }
+
+    impl AsyncBinanceAPI for StubAsyncBinanceAPI {
+        async fn agg_trades(&self,
+            _symbol: &str,
+            _from_id: Option<i64>,
+            start_time: Option<i64>,
+            end_time: Option<i64>,
+            _limit: Option<i64>,
+        ) -> anyhow::Result<String> {
+            let (start_time, end_time) = (start_time.unwrap(), end_time.unwrap());
+            self.requested.lock().unwrap().push((start_time, end_time));
+            self.responses.get(&start_time)
+                .map(|response| response.to_string())
+                .ok_or_else(|| anyhow::Error::msg("unexpected window"))
+        }
+    }
+
+    const MULTIPLE_PRICES_RESPONSE: &str = concat!(
+        r#"[{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "2.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "3.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#
+    );
+    const MULTIPLE_PRICES_RESPONSE_2: &str = concat!(
+        r#"[{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "2.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#,
+    );
+
+    #[tokio::test]
+    async fn test_async_provider_returns_average_prices_from_multiple_time_windows() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let first_window_end = start_time + Duration::minutes(1);
+        let end_time = first_window_end + Duration::seconds(1);
+
+        let stub_api = StubAsyncBinanceAPI {
+            responses: HashMap::from([
+                (start_time.timestamp_millis(), MULTIPLE_PRICES_RESPONSE),
+                (first_window_end.timestamp_millis(), MULTIPLE_PRICES_RESPONSE_2),
+            ]),
+            requested: Mutex::new(Vec::new()),
+        };
+
+        let provider = AsyncBinancePriceProvider::new(stub_api);
+        let prices = provider.prices("BTCUSDC", &start_time, &end_time).await.unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[0].timestamp, start_time );
+        assert_eq!( prices[1].price, dec!(1.5) );
+        assert_eq!( prices[1].timestamp, first_window_end );
+        assert_eq!( *provider.binance_api.requested.lock().unwrap(), vec![
+            (start_time.timestamp_millis(), first_window_end.timestamp_millis() - 1),
+            (first_window_end.timestamp_millis(), end_time.timestamp_millis()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_async_provider_returns_error_on_api_error() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = start_time + Duration::seconds(30);
+
+        let stub_api = StubAsyncBinanceAPI {
+            responses: HashMap::new(),
+            requested: Mutex::new(Vec::new()),
+        };
+
+        let provider = AsyncBinancePriceProvider::new(stub_api);
+        assert!( provider.prices("BTCUSDC", &start_time, &end_time).await.is_err() );
+    }
+}
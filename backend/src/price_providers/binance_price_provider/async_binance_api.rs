@@ -0,0 +1,152 @@
+use super::binance_api::check_limit;
+use std::future::Future;
+
+/// Non-blocking counterpart of `BinanceAPI`, see it for the endpoint documentation.
+///
+/// Methods are declared returning `impl Future + Send` (rather than `async fn`) so the
+/// futures can be spawned on a multi-threaded runtime. Implementors can still use `async fn`.
+pub trait AsyncBinanceAPI {
+    /// GET /api/v3/aggTrades
+    fn agg_trades(&self,
+        symbol: &str,
+        from_id: Option<i64>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send;
+}
+
+pub struct AsyncBinanceHttpClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl AsyncBinanceHttpClient {
+    pub fn new() -> Self {
+        Self::with_base_url("https://api.binance.com")
+    }
+
+    /// See `BinanceHttpClient::with_base_url`.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Default for AsyncBinanceHttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncBinanceAPI for AsyncBinanceHttpClient {
+
+    async fn agg_trades(&self,
+        symbol: &str,
+        from_id: Option<i64>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String> {
+
+        check_limit(limit)?;
+
+        let mut req = self.client.get(format!("{}/api/v3/aggTrades", self.base_url))
+            .query(&[("symbol", symbol)]);
+
+        for (key, value) in [
+            ("fromId", &from_id),
+            ("startTime", &start_time),
+            ("endTime", &end_time),
+            ("limit", &limit),
+        ] {
+            if let Some(v) = value {
+                req = req.query(&[(key, &v.to_string())]);
+            }
+        }
+
+        let resp = req.send().await?.error_for_status()?;
+
+        let text = resp.text().await?;
+        Ok(text)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    impl AsyncBinanceHttpClient {
+        pub fn new_with_test_endpoint() -> Self {
+            Self::with_base_url(&mockito::server_url())
+        }
+    }
+
+    fn server_mock(return_status: usize, response: &str) -> mockito::Mock {
+        server_mock_builder(return_status, response).create()
+    }
+
+    /// Mock not yet created so expectations can still be set on it
+    fn server_mock_builder(return_status: usize, response: &str) -> mockito::Mock {
+        mock("GET", "/api/v3/aggTrades")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("symbol".into(), "ETHUSDT".into()),
+                Matcher::UrlEncoded("startTime".into(), "100".into()),
+                Matcher::UrlEncoded("endTime".into(), "500".into()),
+            ]))
+            .with_status(return_status)
+            .with_body(response)
+    }
+
+    #[tokio::test]
+    async fn test_async_agg_trades_success() {
+        let _m = server_mock(200, "a response");
+
+        let client = AsyncBinanceHttpClient::new_with_test_endpoint();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        ).await;
+        assert_eq!(result.unwrap(), "a response");
+    }
+
+    #[tokio::test]
+    async fn test_async_agg_trades_error() {
+        let _m = server_mock(500, "Internal Server Error");
+
+        let client = AsyncBinanceHttpClient::new_with_test_endpoint();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        ).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_async_agg_trades_rejects_limit_over_max_without_request() {
+        let _m = server_mock_builder(200, "a response").expect(0).create();
+
+        let client = AsyncBinanceHttpClient::new_with_test_endpoint();
+        for limit in [1001, 0] {
+            let result = client.agg_trades(
+                "ETHUSDT",
+                None,
+                Some(100),
+                Some(500),
+                Some(limit),
+            ).await;
+            assert!(result.unwrap_err().to_string().contains("Invalid limit"));
+        }
+        _m.assert();
+    }
+}
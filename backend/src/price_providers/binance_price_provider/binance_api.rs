@@ -1,4 +1,19 @@
-use serde::Deserialize;
+use super::rate_limiter::RateLimiter;
+use crate::retry::retry_with;
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+use sha2::Sha256;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub use crate::retry::{BackoffStrategy, DecorrelatedJitterBackoff, ExponentialBackoff, FixedBackoff, FullJitterBackoff, LinearBackoff, RetryPolicy};
 
 pub trait BinanceAPI { 
     /// GET /api/v3/aggTrades
@@ -30,6 +45,145 @@ pub trait BinanceAPI {
         end_time: Option<i64>,
         limit: Option<i64>,
     ) -> anyhow::Result<String>;
+
+    /// POST /api/v3/userDataStream
+    ///
+    /// Requires the `X-MBX-APIKEY` header.
+    /// Starts a new user data stream, the key stays valid for 60 minutes.
+    ///
+    /// Expected Response:
+    /// {
+    ///   "listenKey": "pqia91ma19a5s61cv6a81va65sdf19v8a65a1a5s61cv6a81va65sdf19v8a65a1"
+    /// }
+    fn create_listen_key(&self) -> anyhow::Result<String>;
+
+    /// PUT /api/v3/userDataStream
+    ///
+    /// Requires the `X-MBX-APIKEY` header.
+    /// Extends the validity of a listen key by 60 minutes.
+    ///
+    /// Parameters
+    /// listenKey   STRING  YES
+    ///
+    /// Expected Response:
+    /// {}
+    fn keepalive_listen_key(&self, listen_key: &str) -> anyhow::Result<()>;
+
+    /// GET /api/v3/exchangeInfo
+    ///
+    /// Expected Response (trimmed to the fields in use):
+    /// {
+    ///   "symbols": [
+    ///     {
+    ///       "symbol": "ETHBTC",
+    ///       "status": "TRADING",
+    ///       "filters": [
+    ///         { "filterType": "PRICE_FILTER", "minPrice": "0.00000100", "maxPrice": "100000.00000000", "tickSize": "0.00000100" }
+    ///       ]
+    ///     }
+    ///   ]
+    /// }
+    fn exchange_info(&self) -> anyhow::Result<String>;
+
+    /// GET /api/v3/klines
+    ///
+    /// Parameters
+    /// symbol      STRING  YES
+    /// interval    ENUM    YES  1s, 1m, 3m, 5m, 15m, 30m, 1h, 2h, 4h, 6h, 8h, 12h, 1d, 3d, 1w, 1M
+    /// startTime   LONG    NO
+    /// endTime     LONG    NO
+    /// limit       INT     NO  Default 500; max 1000.
+    ///
+    /// Expected Response:
+    /// [
+    ///   [
+    ///     1499040000000,      // Kline open time
+    ///     "0.01634790",       // Open price
+    ///     "0.80000000",       // High price
+    ///     "0.01575800",       // Low price
+    ///     "0.01577100",       // Close price
+    ///     "148976.11427815",  // Volume
+    ///     1499644799999,      // Kline close time
+    ///     "2434.19055334",    // Quote asset volume
+    ///     308,                // Number of trades
+    ///     "1756.87402397",    // Taker buy base asset volume
+    ///     "28.46694368",      // Taker buy quote asset volume
+    ///     "0"                 // Unused field, ignore.
+    ///   ]
+    /// ]
+    fn klines(&self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String>;
+
+    /// GET /api/v3/ticker/24hr
+    ///
+    /// Parameters
+    /// symbol      STRING  YES
+    ///
+    /// Expected Response (trimmed to the fields in use):
+    /// {
+    ///   "symbol": "BNBBTC",
+    ///   "priceChangePercent": "-95.960",
+    ///   "lastPrice": "4.00000200",
+    ///   "highPrice": "100.00000000",
+    ///   "lowPrice": "0.10000000",
+    ///   "volume": "8913.30000000"
+    /// }
+    fn ticker_24hr(&self, symbol: &str) -> anyhow::Result<String>;
+
+    /// GET /api/v3/time
+    ///
+    /// Expected Response:
+    /// {
+    ///   "serverTime": 1499827319559
+    /// }
+    ///
+    /// Returns the `serverTime` millis.
+    fn server_time(&self) -> anyhow::Result<i64>;
+
+    /// GET /api/v3/ping
+    ///
+    /// Test connectivity to the REST API.
+    ///
+    /// Expected Response:
+    /// {}
+    fn ping(&self) -> anyhow::Result<()>;
+
+    /// GET /api/v3/depth
+    ///
+    /// Parameters
+    /// symbol      STRING  YES
+    /// limit       INT     NO  Default 100; max 5000.
+    ///
+    /// Expected Response (bids best first, i.e. highest, asks lowest first):
+    /// {
+    ///   "lastUpdateId": 1027024,
+    ///   "bids": [
+    ///     ["4.00000000", "431.00000000"]   // [price, quantity]
+    ///   ],
+    ///   "asks": [
+    ///     ["4.00000200", "12.00000000"]
+    ///   ]
+    /// }
+    fn depth(&self, symbol: &str, limit: Option<i64>) -> anyhow::Result<String>;
+
+    /// GET /api/v3/avgPrice
+    ///
+    /// Current average price, over the last `mins` minutes, in a single low-weight call.
+    ///
+    /// Parameters
+    /// symbol      STRING  YES
+    ///
+    /// Expected Response:
+    /// {
+    ///   "mins": 5,
+    ///   "price": "9.35751834"
+    /// }
+    fn avg_price(&self, symbol: &str) -> anyhow::Result<String>;
 }
 
 #[derive(Deserialize)]
@@ -46,23 +200,606 @@ pub struct AggTradesResponseItem {
 }
 pub type AggTradesResponse = Vec<AggTradesResponseItem>;
 
+/// Same as `AggTradesResponseItem` but fails on fields Binance didn't document,
+/// so schema drift can be noticed.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(non_snake_case)]
+pub struct StrictAggTradesResponseItem {
+    pub a: i64,
+    pub p: String,
+    pub q: String,
+    pub f: i64,
+    pub l: i64,
+    pub T: i64,
+    pub m: bool,
+    pub M: bool,
+}
+pub type StrictAggTradesResponse = Vec<StrictAggTradesResponseItem>;
+
+impl From<StrictAggTradesResponseItem> for AggTradesResponseItem {
+    fn from(item: StrictAggTradesResponseItem) -> Self {
+        AggTradesResponseItem { a: item.a, p: item.p, q: item.q, f: item.f, l: item.l, T: item.T, m: item.m, M: item.M }
+    }
+}
+
+/// An aggregate trade with its price and quantity parsed while deserializing, unlike the
+/// raw `AggTradesResponseItem`. A bad number fails with an error naming the field and value.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct AggTrade {
+    #[serde(rename = "a")]
+    pub id: i64,
+    #[serde(rename = "p", deserialize_with = "price_field")]
+    pub price: Decimal,
+    #[serde(rename = "q", deserialize_with = "quantity_field")]
+    pub quantity: Decimal,
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    #[serde(rename = "l")]
+    pub last_trade_id: i64,
+    #[serde(rename = "T")]
+    pub time: i64,
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+    #[serde(rename = "M")]
+    pub is_best_match: bool,
+}
+pub type AggTrades = Vec<AggTrade>;
+
+fn decimal_field<'de, D: Deserializer<'de>>(deserializer: D, field: &str) -> Result<Decimal, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Decimal::from_str(&value).map_err(|_| serde::de::Error::custom(format!("invalid number {:?} in field `{}`", value, field)))
+}
+
+fn price_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    decimal_field(deserializer, "p")
+}
+
+fn quantity_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    decimal_field(deserializer, "q")
+}
+
+/// A kline as sent by Binance: a positional array of mixed numbers and strings.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct KlineRow(i64, String, String, String, String, String, i64, String, i64, String, String, String);
+
+#[derive(Deserialize)]
+#[serde(from = "KlineRow")]
+pub struct Kline {
+    pub open_time: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    pub close_time: i64,
+    pub number_of_trades: i64,
+}
+
+impl From<KlineRow> for Kline {
+    fn from(row: KlineRow) -> Self {
+        Kline {
+            open_time: row.0,
+            open: row.1,
+            high: row.2,
+            low: row.3,
+            close: row.4,
+            volume: row.5,
+            close_time: row.6,
+            number_of_trades: row.8,
+        }
+    }
+}
+pub type KlinesResponse = Vec<Kline>;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ticker24hr {
+    pub last_price: String,
+    pub price_change_percent: String,
+    pub high_price: String,
+    pub low_price: String,
+    pub volume: String,
+}
+
+/// Rolling average price from `BinanceAPI::avg_price`.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct AvgPrice {
+    pub mins: u32,
+    pub price: String,
+}
+
+#[derive(Deserialize)]
+struct OrderBookResponse {
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+/// `(price, quantity)` levels of an order book, best first, from `BinanceAPI::depth`.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(try_from = "OrderBookResponse")]
+pub struct OrderBook {
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+}
+
+impl TryFrom<OrderBookResponse> for OrderBook {
+    type Error = String;
+
+    fn try_from(response: OrderBookResponse) -> Result<Self, Self::Error> {
+        let levels = |side: Vec<(String, String)>| side.into_iter()
+            .map(|(price, quantity)| match (price.parse::<f64>(), quantity.parse::<f64>()) {
+                (Ok(price), Ok(quantity)) => Ok((price, quantity)),
+                _ => Err(format!("invalid order book level [{:?}, {:?}]", price, quantity)),
+            })
+            .collect::<Result<Vec<_>, _>>();
+        Ok(OrderBook { bids: levels(response.bids)?, asks: levels(response.asks)? })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTimeResponse {
+    pub server_time: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenKeyResponse {
+    pub listen_key: String,
+}
+
+/// Body Binance sends instead of the expected payload when a request fails,
+/// e.g. `{"code":-1121,"msg":"Invalid symbol."}`
+#[derive(Deserialize)]
+pub struct BinanceErrorResponse {
+    pub code: i64,
+    pub msg: String,
+}
+
+/// A 4xx response carrying Binance's error envelope, the status error is the source.
+#[derive(Debug, thiserror::Error)]
+#[error("Binance error {code}: {msg}")]
+pub struct BinanceApiError {
+    pub code: i64,
+    pub msg: String,
+    #[source]
+    pub source: reqwest::Error,
+}
+
+/// The error envelope of a client error response other than 429, which Binance
+/// sends e.g. for an unknown symbol. Other errors don't explain more than their status.
+fn error_response(resp: Response) -> Option<BinanceErrorResponse> {
+    let status = resp.status();
+    if !status.is_client_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    resp.text().ok().and_then(|body| serde_json::from_str(&body).ok())
+}
+
+#[derive(Deserialize)]
+pub struct ExchangeInfoResponse {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub status: String,
+    #[serde(default)]
+    pub filters: Vec<SymbolFilter>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolFilter {
+    pub filter_type: String,
+    pub tick_size: Option<String>,
+}
+
+const API_KEY_HEADER: &str = "X-MBX-APIKEY";
+const RETRY_AFTER_HEADER: &str = "Retry-After";
+const USED_WEIGHT_HEADER: &str = "X-MBX-USED-WEIGHT-1M";
+pub const DEFAULT_CORRELATION_HEADER: &str = "X-Request-Id";
+/// Request weight Binance charges for an aggTrades call.
+const AGG_TRADES_WEIGHT: u32 = 2;
+/// Largest `limit` Binance accepts for aggTrades and klines, it answers 400 above it.
+pub const MAX_LIMIT: i64 = 1000;
+/// Default limit for a whole request, from connecting until the body is read.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Idle connections kept open to Binance, enough for `prices_parallel` workers to each reuse one.
+pub const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 8;
+/// How long an idle connection is kept, long enough to outlast the pause between windows.
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Identifies us to Binance, anonymous clients may be throttled harder.
+pub const DEFAULT_USER_AGENT: &str = concat!("rust_practice/", env!("CARGO_PKG_VERSION"));
+
+/// Fails for a `limit` outside `1..=MAX_LIMIT`, which Binance would reject anyway.
+/// Values are rejected rather than clamped so callers don't silently get fewer rows.
+pub(super) fn check_limit(limit: Option<i64>) -> anyhow::Result<()> {
+    match limit {
+        Some(limit) if !(1..=MAX_LIMIT).contains(&limit) => anyhow::bail!("Invalid limit {}, must be between 1 and {}", limit, MAX_LIMIT),
+        _ => Ok(()),
+    }
+}
+
+/// Seconds to wait as requested by a 429 response, if any.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    if resp.status() != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    resp.headers().get(RETRY_AFTER_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn is_retryable(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => err.is_connect() || err.is_timeout(),
+    }
+}
+
+/// Snapshot of the counters of a `BinanceHttpClient`, see `BinanceHttpClient::metrics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClientMetrics {
+    /// Requests made, each counted once however many attempts it took.
+    pub requests_total: u64,
+    /// Requests that still failed after their retries.
+    pub requests_failed: u64,
+    /// Attempts beyond the first one.
+    pub retries_total: u64,
+}
+
+#[derive(Default)]
+struct Metrics {
+    requests_total: AtomicU64,
+    requests_failed: AtomicU64,
+    retries_total: AtomicU64,
+}
+
+/// Settings baked into the reqwest client, changing any of them rebuilds it.
+struct ClientOptions {
+    compression: bool,
+    timeout: Duration,
+    user_agent: String,
+    /// Sent with every request besides the `User-Agent`.
+    headers: HeaderMap,
+    /// Replaces the proxies reqwest reads from `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`.
+    proxy: Option<reqwest::Proxy>,
+    pool_max_idle_per_host: usize,
+    /// `None` keeps idle connections forever.
+    pool_idle_timeout: Option<Duration>,
+}
+
+impl ClientOptions {
+    fn build_client(&self) -> reqwest::blocking::Client {
+        let mut builder = reqwest::blocking::Client::builder()
+            .gzip(self.compression)
+            .timeout(self.timeout)
+            .user_agent(&self.user_agent)
+            .default_headers(self.headers.clone())
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        builder.build().expect("Failed to build HTTP client")
+    }
+}
 
 pub struct BinanceHttpClient {
     client: reqwest::blocking::Client,
-    agg_trades_endpoint: String,
+    options: ClientOptions,
+    base_url: String,
+    api_key: Option<String>,
+    secret_key: Option<String>,
+    retry_policy: RetryPolicy,
+    correlation_header: String,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    metrics: Metrics,
+    last_used_weight: Mutex<Option<u32>>,
+    /// Millis Binance's clock is ahead of ours, added to signed request timestamps.
+    clock_offset_ms: AtomicI64,
+    recv_window: Option<Duration>,
 }
 
 impl BinanceHttpClient {
     pub fn new() -> Self {
+        Self::with_base_url("https://api.binance.com")
+    }
+
+    /// Client for another Binance deployment, e.g. `https://testnet.binance.vision`
+    /// or a mirror like `https://api-gcp.binance.com`. All endpoints derive from `base_url`.
+    pub fn with_base_url(base_url: &str) -> Self {
+        let options = ClientOptions {
+            compression: true,
+            timeout: DEFAULT_TIMEOUT,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: HeaderMap::new(),
+            proxy: None,
+            pool_max_idle_per_host: DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+        };
         Self {
-            client: reqwest::blocking::Client::new(),
-            agg_trades_endpoint: "https://api.binance.com/api/v3/aggTrades".to_string(),
+            client: options.build_client(),
+            options,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            secret_key: None,
+            retry_policy: RetryPolicy::default(),
+            correlation_header: DEFAULT_CORRELATION_HEADER.to_string(),
+            rate_limiter: None,
+            metrics: Metrics::default(),
+            last_used_weight: Mutex::new(None),
+            clock_offset_ms: AtomicI64::new(0),
+            recv_window: None,
+        }
+    }
+
+    /// Whether to send `Accept-Encoding: gzip`, on by default.
+    /// Compressed responses are decompressed transparently.
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.options.compression = enabled;
+        self.client = self.options.build_client();
+        self
+    }
+
+    /// Fails requests Binance doesn't fully answer within `timeout`, `DEFAULT_TIMEOUT` by default.
+    /// Timeouts count as transient failures and are retried.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self.client = self.options.build_client();
+        self
+    }
+
+    /// `User-Agent` sent with every request, `DEFAULT_USER_AGENT` by default.
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.options.user_agent = user_agent.to_string();
+        self.client = self.options.build_client();
+        self
+    }
+
+    /// Sends `name: value` with every request, replacing any previous value of `name`.
+    /// Fails when either isn't a valid header.
+    pub fn with_header(mut self, name: &str, value: &str) -> anyhow::Result<Self> {
+        let name = HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid header name {:?}", name))?;
+        let value = HeaderValue::from_str(value).with_context(|| format!("Invalid value for header {}", name))?;
+        self.options.headers.insert(name, value);
+        self.client = self.options.build_client();
+        Ok(self)
+    }
+
+    /// Sends every request through the proxy at `url`, e.g. `http://proxy.corp:3128`
+    /// or `socks5://127.0.0.1:1080`. Fails when `url` isn't a valid proxy url.
+    ///
+    /// Without it the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` env vars are honored.
+    pub fn with_proxy(mut self, url: &str) -> anyhow::Result<Self> {
+        let proxy = reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy url {:?}", url))?;
+        self.options.proxy = Some(proxy);
+        self.client = self.options.build_client();
+        Ok(self)
+    }
+
+    /// Idle connections kept open, `DEFAULT_POOL_MAX_IDLE_PER_HOST` by default.
+    ///
+    /// Reused connections skip the TCP and TLS handshakes, which adds up over the
+    /// thousands of sequential requests of a long backfill. 0 disables reuse.
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.options.pool_max_idle_per_host = max_idle;
+        self.client = self.options.build_client();
+        self
+    }
+
+    /// How long idle connections are kept for reuse, `DEFAULT_POOL_IDLE_TIMEOUT` by default
+    /// and forever with `None`.
+    pub fn with_pool_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.options.pool_idle_timeout = idle_timeout;
+        self.client = self.options.build_client();
+        self
+    }
+
+    /// Tells timeouts apart from other failures in the error message.
+    fn describe_error(&self, err: reqwest::Error) -> anyhow::Error {
+        if err.is_timeout() {
+            let timeout = self.options.timeout;
+            anyhow::Error::new(err).context(format!("Binance didn't answer within {:?}", timeout))
+        } else {
+            anyhow::Error::new(err)
+        }
+    }
+
+    fn read_text(&self, resp: Response) -> anyhow::Result<String> {
+        resp.text().map_err(|err| self.describe_error(err))
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/api/v3/{}", self.base_url, path)
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Header carrying the random UUID sent with each request, `X-Request-Id` by default.
+    /// Retries of a request reuse its id, and errors include it to match them with proxy logs.
+    pub fn with_correlation_header(mut self, header: &str) -> Self {
+        self.correlation_header = header.to_string();
+        self
+    }
+
+    /// Takes permits from `rate_limiter` before each aggTrades attempt, retries included,
+    /// blocking while it's drained.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Sends the request retrying transient failures as configured by the `RetryPolicy`.
+    /// Client errors (4xx) other than 429 are returned right away.
+    /// A 429 carrying a `Retry-After` header waits that long instead of the backoff delay.
+    fn send_with_retry(&self, request: RequestBuilder) -> anyhow::Result<Response> {
+        self.send_weighted_with_retry(request, None)
+    }
+
+    /// Same as `send_with_retry` taking `weight` permits from the `rate_limiter`, if any,
+    /// before every attempt.
+    fn send_weighted_with_retry(&self, request: RequestBuilder, weight: Option<u32>) -> anyhow::Result<Response> {
+        anyhow::ensure!(request.try_clone().is_some(), "Request can't be retried");
+        self.send_built_with_retry(|| request.try_clone().expect("checked above"), weight)
+    }
+
+    /// Same as `send_weighted_with_retry` but building the request anew for each attempt,
+    /// e.g. so a signed request gets a fresh timestamp.
+    fn send_built_with_retry(&self, build: impl Fn() -> RequestBuilder, weight: Option<u32>) -> anyhow::Result<Response> {
+        self.metrics.requests_total.fetch_add(1, Ordering::Relaxed);
+        let result = self.send_attempts(build, weight);
+        if result.is_err() {
+            self.metrics.requests_failed.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn send_attempts(&self, build: impl Fn() -> RequestBuilder, weight: Option<u32>) -> anyhow::Result<Response> {
+        let correlation_id = uuid::Uuid::new_v4().to_string();
+        let mut attempts = 0;
+        let send = || {
+            attempts += 1;
+            if let (Some(rate_limiter), Some(weight)) = (&self.rate_limiter, weight) {
+                rate_limiter.acquire(weight);
+            }
+            let resp = build().header(&self.correlation_header, &correlation_id).send().map_err(|err| (err, None, None))?;
+            self.record_used_weight(&resp);
+            let retry_after = retry_after(&resp);
+            if let Err(err) = resp.error_for_status_ref() {
+                return Err((err, retry_after, error_response(resp)));
+            }
+            Ok(resp)
+        };
+        let next_delay = |(err, retry_after, _): &(reqwest::Error, Option<Duration>, Option<BinanceErrorResponse>), attempt: u32| {
+            if !is_retryable(err) {
+                return None;
+            }
+            let delay = retry_after.unwrap_or_else(|| self.retry_policy.delay(attempt));
+            tracing::debug!(%correlation_id, attempt, error = %err, ?delay, "Retrying request");
+            self.metrics.retries_total.fetch_add(1, Ordering::Relaxed);
+            Some(delay)
+        };
+        retry_with(&self.retry_policy, send, next_delay).map_err(|(err, _, error_response)| {
+            tracing::warn!(%correlation_id, attempts, error = %err, "Request failed");
+            let err = match error_response {
+                Some(BinanceErrorResponse { code, msg }) => anyhow::Error::new(BinanceApiError { code, msg, source: err }),
+                None => self.describe_error(err),
+            };
+            err.context(format!("Request {} failed after {} attempt(s)", correlation_id, attempts))
+        })
+    }
+
+    /// Counters of every request sent through `send_with_retry` so far, shared by every
+    /// thread using this client.
+    pub fn metrics(&self) -> ClientMetrics {
+        ClientMetrics {
+            requests_total: self.metrics.requests_total.load(Ordering::Relaxed),
+            requests_failed: self.metrics.requests_failed.load(Ordering::Relaxed),
+            retries_total: self.metrics.retries_total.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_used_weight(&self, resp: &Response) {
+        let used_weight = resp.headers().get(USED_WEIGHT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u32>().ok());
+        if used_weight.is_some() {
+            *self.last_used_weight.lock().unwrap() = used_weight;
+        }
+    }
+
+    /// Request weight used in the current minute as reported by the last Binance response.
+    /// Callers can use it to throttle before hitting the limit.
+    pub fn last_used_weight(&self) -> Option<u32> {
+        *self.last_used_weight.lock().unwrap()
+    }
+
+    /// Sets the API key sent on endpoints that require one (e.g. user data streams).
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    fn api_key(&self) -> anyhow::Result<&str> {
+        self.api_key.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("An API key is required for this endpoint"))
+    }
+
+    /// Sets the secret key signing requests made with `signed_get`.
+    pub fn with_secret_key(mut self, secret_key: &str) -> Self {
+        self.secret_key = Some(secret_key.to_string());
+        self
+    }
+
+    /// Sends `recvWindow` with signed requests, how long after its `timestamp` Binance
+    /// still accepts one. Binance's default of 5 seconds applies when unset.
+    pub fn with_recv_window(mut self, recv_window: Duration) -> Self {
+        self.recv_window = Some(recv_window);
+        self
+    }
+
+    /// How far Binance's clock is ahead of ours, e.g. from `BinancePriceProvider::clock_offset`.
+    /// Signed request timestamps are shifted by it so a skewed local clock isn't rejected (-1021).
+    pub fn set_clock_offset(&self, offset: chrono::Duration) {
+        self.clock_offset_ms.store(offset.num_milliseconds(), Ordering::Relaxed);
+    }
+
+    /// GET on a `USER_DATA` endpoint (e.g. `account`), which Binance requires to be signed.
+    ///
+    /// Appends `recvWindow` if set, the current `timestamp` and the `signature` to `params`
+    /// and sends the API key. Each attempt is signed anew, so a retry isn't rejected for
+    /// falling outside `recvWindow`. Requires both `with_api_key` and `with_secret_key`.
+    pub fn signed_get(&self, endpoint: &str, params: &[(&str, &str)]) -> anyhow::Result<String> {
+        let api_key = self.api_key()?;
+        let secret_key = self.secret_key.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("A secret key is required to sign requests"))?;
+        let recv_window = self.recv_window.map(|recv_window| recv_window.as_millis().to_string());
+        let mut params = params.to_vec();
+        if let Some(recv_window) = &recv_window {
+            params.push(("recvWindow", recv_window));
         }
+        let resp = self.send_built_with_retry(|| {
+            let timestamp = chrono::Utc::now().timestamp_millis() + self.clock_offset_ms.load(Ordering::Relaxed);
+            let query = signed_query(secret_key, &params, timestamp);
+            self.client.get(format!("{}?{}", self.endpoint(endpoint), query))
+                .header(API_KEY_HEADER, api_key)
+        }, None)?;
+        self.read_text(resp)
+    }
+}
+
+/// Url encoded `params` followed by `timestamp` and their HMAC-SHA256 `signature`.
+fn signed_query(secret_key: &str, params: &[(&str, &str)], timestamp: i64) -> String {
+    let timestamp = timestamp.to_string();
+    let query = params.iter()
+        .chain(std::iter::once(&("timestamp", timestamp.as_str())))
+        .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret_key.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(query.as_bytes());
+    format!("{}&signature={}", query, hex::encode(mac.finalize().into_bytes()))
+}
+
+impl Default for BinanceHttpClient {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl BinanceAPI for BinanceHttpClient {
 
+    #[tracing::instrument(level = "debug", skip(self))]
     fn agg_trades(&self, 
         symbol: &str,
         from_id: Option<i64>,
@@ -71,7 +808,9 @@ impl BinanceAPI for BinanceHttpClient {
         limit: Option<i64>,
     ) -> anyhow::Result<String> {
 
-        let mut req = self.client.get(&self.agg_trades_endpoint)
+        check_limit(limit)?;
+
+        let mut req = self.client.get(self.endpoint("aggTrades"))
             .query(&[("symbol", symbol)]);
 
         for (key, value) in [
@@ -85,12 +824,143 @@ impl BinanceAPI for BinanceHttpClient {
             }
         }
 
-        let resp = req.send()?.error_for_status()?;
+        let resp = self.send_weighted_with_retry(req, Some(AGG_TRADES_WEIGHT))?;
+
+        self.read_text(resp)
+    }
+
+    fn create_listen_key(&self) -> anyhow::Result<String> {
+        // Binance hands back the active key when there's one, retrying doesn't open a second stream
+        let resp = self.send_with_retry(self.client.post(self.endpoint("userDataStream"))
+            .header(API_KEY_HEADER, self.api_key()?))?;
+
+        let response_json: ListenKeyResponse = serde_json::from_str(&self.read_text(resp)?)?;
+        Ok(response_json.listen_key)
+    }
+
+    fn keepalive_listen_key(&self, listen_key: &str) -> anyhow::Result<()> {
+        self.send_with_retry(self.client.put(self.endpoint("userDataStream"))
+            .header(API_KEY_HEADER, self.api_key()?)
+            .query(&[("listenKey", listen_key)]))?;
+        Ok(())
+    }
+
+    fn exchange_info(&self) -> anyhow::Result<String> {
+        let resp = self.send_with_retry(self.client.get(self.endpoint("exchangeInfo")))?;
+        self.read_text(resp)
+    }
+
+    fn klines(&self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String> {
+
+        check_limit(limit)?;
+        let mut req = self.client.get(self.endpoint("klines"))
+            .query(&[("symbol", symbol), ("interval", interval)]);
+
+        for (key, value) in [
+            ("startTime", &start_time),
+            ("endTime", &end_time),
+            ("limit", &limit),
+        ] {
+            if let Some(v) = value {
+                req = req.query(&[(key, &v.to_string())]);
+            }
+        }
+
+        let resp = self.send_with_retry(req)?;
+
+        self.read_text(resp)
+    }
+
+    fn ticker_24hr(&self, symbol: &str) -> anyhow::Result<String> {
+        let req = self.client.get(self.endpoint("ticker/24hr"))
+            .query(&[("symbol", symbol)]);
+
+        let resp = self.send_with_retry(req)?;
+
+        self.read_text(resp)
+    }
+
+    fn server_time(&self) -> anyhow::Result<i64> {
+        let resp = self.send_with_retry(self.client.get(self.endpoint("time")))?;
+        let response_json: ServerTimeResponse = serde_json::from_str(&self.read_text(resp)?)?;
+        Ok(response_json.server_time)
+    }
+
+    fn ping(&self) -> anyhow::Result<()> {
+        self.send_with_retry(self.client.get(self.endpoint("ping")))?;
+        Ok(())
+    }
+
+    fn depth(&self, symbol: &str, limit: Option<i64>) -> anyhow::Result<String> {
+        let mut req = self.client.get(self.endpoint("depth"))
+            .query(&[("symbol", symbol)]);
+        if let Some(limit) = limit {
+            req = req.query(&[("limit", &limit.to_string())]);
+        }
+
+        let resp = self.send_with_retry(req)?;
+
+        self.read_text(resp)
+    }
+
+    fn avg_price(&self, symbol: &str) -> anyhow::Result<String> {
+        let req = self.client.get(self.endpoint("avgPrice"))
+            .query(&[("symbol", symbol)]);
+
+        let resp = self.send_with_retry(req)?;
+
+        self.read_text(resp)
+    }
+
+}
+
+/// Lets one client be shared, e.g. by a provider and whoever reads its `metrics`.
+impl<T: BinanceAPI + ?Sized> BinanceAPI for Arc<T> {
+    fn agg_trades(&self, symbol: &str, from_id: Option<i64>, start_time: Option<i64>, end_time: Option<i64>, limit: Option<i64>) -> anyhow::Result<String> {
+        (**self).agg_trades(symbol, from_id, start_time, end_time, limit)
+    }
+
+    fn create_listen_key(&self) -> anyhow::Result<String> {
+        (**self).create_listen_key()
+    }
+
+    fn keepalive_listen_key(&self, listen_key: &str) -> anyhow::Result<()> {
+        (**self).keepalive_listen_key(listen_key)
+    }
+
+    fn exchange_info(&self) -> anyhow::Result<String> {
+        (**self).exchange_info()
+    }
+
+    fn klines(&self, symbol: &str, interval: &str, start_time: Option<i64>, end_time: Option<i64>, limit: Option<i64>) -> anyhow::Result<String> {
+        (**self).klines(symbol, interval, start_time, end_time, limit)
+    }
+
+    fn ticker_24hr(&self, symbol: &str) -> anyhow::Result<String> {
+        (**self).ticker_24hr(symbol)
+    }
+
+    fn server_time(&self) -> anyhow::Result<i64> {
+        (**self).server_time()
+    }
+
+    fn ping(&self) -> anyhow::Result<()> {
+        (**self).ping()
+    }
 
-        let text = resp.text()?;
-        Ok(text)
+    fn depth(&self, symbol: &str, limit: Option<i64>) -> anyhow::Result<String> {
+        (**self).depth(symbol, limit)
     }
 
+    fn avg_price(&self, symbol: &str) -> anyhow::Result<String> {
+        (**self).avg_price(symbol)
+    }
 }
 
 #[cfg(test)]
@@ -100,10 +970,11 @@ mod tests {
 
     impl BinanceHttpClient {
         pub fn new_with_test_endpoint() -> Self {
-            Self {
-                client: reqwest::blocking::Client::new(),
-                agg_trades_endpoint: format!("{}/api/v3/aggTrades", &mockito::server_url()),
-            }
+            Self::with_base_url(&mockito::server_url())
+                .with_retry_policy(RetryPolicy {
+                    max_retries: 3,
+                    backoff: Arc::new(FixedBackoff(Duration::from_millis(1))),
+                })
         }
     }
 
@@ -112,6 +983,11 @@ mod tests {
     // and get rid of these
 
     fn server_mock(return_status: usize, response: &str) -> mockito::Mock {
+        server_mock_builder(return_status, response).create()
+    }
+
+    /// Mock not yet created so expectations can still be set on it
+    fn server_mock_builder(return_status: usize, response: &str) -> mockito::Mock {
         mock("GET", "/api/v3/aggTrades")
             .match_query(Matcher::AllOf(vec![
                 Matcher::UrlEncoded("symbol".into(), "ETHUSDT".into()),
@@ -120,7 +996,6 @@ mod tests {
             ]))
             .with_status(return_status)
             .with_body(response)
-            .create()
     }
 
     #[test]
@@ -139,8 +1014,69 @@ mod tests {
     }
 
     #[test]
-    fn test_agg_trades_error() {
-        let _m = server_mock(500, "Internal Server Error");
+    fn test_agg_trades_rejects_limit_over_max_without_request() {
+        let _m = server_mock_builder(200, "a response").expect(0).create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        for limit in [1001, 0] {
+            let result = client.agg_trades(
+                "ETHUSDT",
+                None,
+                Some(100),
+                Some(500),
+                Some(limit),
+            );
+            assert!(result.unwrap_err().to_string().contains("Invalid limit"));
+        }
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_accepts_max_limit() {
+        let _m = mock("GET", "/api/v3/aggTrades")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("symbol".into(), "ETHUSDT".into()),
+                Matcher::UrlEncoded("limit".into(), "1000".into()),
+            ]))
+            .with_status(200)
+            .with_body("a response")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            Some(1000),
+        );
+        assert_eq!(result.unwrap(), "a response");
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_with_custom_pool_settings() {
+        let _m = server_mock_builder(200, "a response").expect(2).create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint()
+            .with_pool_max_idle_per_host(1)
+            .with_pool_idle_timeout(Some(Duration::from_secs(5)));
+        for _ in 0..2 {
+            let result = client.agg_trades(
+                "ETHUSDT",
+                None,
+                Some(100),
+                Some(500),
+                None,
+            );
+            assert_eq!(result.unwrap(), "a response");
+        }
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_error() {
+        let _m = server_mock(500, "Internal Server Error");
 
         let client = BinanceHttpClient::new_with_test_endpoint();
         let result = client.agg_trades(
@@ -152,4 +1088,676 @@ mod tests {
         );
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_agg_trades_retries_transient_errors() {
+        let _m_unavailable = server_mock_builder(503, "Service Unavailable").expect(2).create();
+        let _m_ok = server_mock(200, "a response");
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        );
+        assert_eq!(result.unwrap(), "a response");
+        _m_unavailable.assert();
+        _m_ok.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_does_not_retry_client_errors() {
+        let _m = server_mock_builder(400, "Bad Request").expect(1).create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        );
+        assert!(format!("{:#}", result.unwrap_err()).contains("after 1 attempt(s)"));
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_keeps_binance_error_of_client_errors() {
+        let _m = server_mock(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#);
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let err = client.agg_trades("ETHUSDT", None, Some(100), Some(500), None).unwrap_err();
+
+        let api_error = err.chain().find_map(|cause| cause.downcast_ref::<BinanceApiError>()).unwrap();
+        assert_eq!(api_error.code, -1121);
+        assert_eq!(api_error.msg, "Invalid symbol.");
+        assert_eq!(api_error.source.status(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_agg_trades_gives_up_after_max_retries() {
+        let _m = server_mock_builder(500, "Internal Server Error").expect(4).create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        );
+        assert!(format!("{:#}", result.unwrap_err()).contains("after 4 attempt(s)"));
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_waits_retry_after_on_rate_limit() {
+        let _m_limited = server_mock_builder(429, "Too Many Requests")
+            .with_header(RETRY_AFTER_HEADER, "1")
+            .expect(1)
+            .create();
+        let _m_ok = server_mock(200, "a response");
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let started = std::time::Instant::now();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        );
+        assert_eq!(result.unwrap(), "a response");
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        _m_limited.assert();
+        _m_ok.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_records_used_weight() {
+        let _m = server_mock_builder(200, "a response")
+            .with_header(USED_WEIGHT_HEADER, "42")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        assert_eq!(client.last_used_weight(), None);
+        let _ = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        );
+        assert_eq!(client.last_used_weight(), Some(42));
+    }
+
+    #[test]
+    fn test_agg_trades_times_out_on_stalled_response() {
+        let _m = server_mock_builder(200, "")
+            .with_body_from_fn(|body| {
+                std::thread::sleep(Duration::from_millis(600));
+                body.write_all(b"a response")
+            })
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint()
+            .with_retry_policy(RetryPolicy { max_retries: 0, backoff: Arc::new(FixedBackoff(Duration::ZERO)) })
+            .with_timeout(Duration::from_millis(100));
+        let started = std::time::Instant::now();
+        let result = client.agg_trades(
+            "ETHUSDT",
+            None,
+            Some(100),
+            Some(500),
+            None,
+        );
+
+        assert!(format!("{:#}", result.unwrap_err()).contains("didn't answer within 100ms"));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_agg_trades_waits_for_rate_limiter() {
+        let _m = server_mock(200, "a response");
+        // Room for two calls, then one call every 100ms
+        let rate_limiter = Arc::new(RateLimiter::new(2 * AGG_TRADES_WEIGHT, 20.0));
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_rate_limiter(rate_limiter);
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            client.agg_trades("ETHUSDT", None, Some(100), Some(500), None).unwrap();
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(95));
+        assert!(started.elapsed() < Duration::from_millis(600));
+    }
+
+    #[test]
+    fn test_agg_trades_retries_take_rate_limiter_permits() {
+        let _m_unavailable = server_mock_builder(503, "Service Unavailable").expect(1).create();
+        let _m_ok = server_mock(200, "a response");
+        // Room for one attempt, then one every 100ms
+        let rate_limiter = Arc::new(RateLimiter::new(AGG_TRADES_WEIGHT, 20.0));
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_rate_limiter(rate_limiter);
+        let started = std::time::Instant::now();
+        client.agg_trades("ETHUSDT", None, Some(100), Some(500), None).unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(95));
+        _m_unavailable.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_counts_requests_failures_and_retries() {
+        let _m_ok = server_mock(200, "a response");
+        let _m_failing = mock("GET", "/api/v3/aggTrades")
+            .match_query(Matcher::UrlEncoded("startTime".into(), "200".into()))
+            .with_status(500)
+            .expect(2)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint()
+            .with_retry_policy(RetryPolicy { max_retries: 1, backoff: Arc::new(FixedBackoff(Duration::from_millis(1))) });
+        assert_eq!(client.metrics(), ClientMetrics::default());
+        for _ in 0..2 {
+            client.agg_trades("ETHUSDT", None, Some(100), Some(500), None).unwrap();
+        }
+        assert!(client.agg_trades("ETHUSDT", None, Some(200), Some(500), None).is_err());
+
+        assert_eq!(client.metrics(), ClientMetrics { requests_total: 3, requests_failed: 1, retries_total: 1 });
+        _m_failing.assert();
+    }
+
+    #[test]
+    fn test_server_time_success() {
+        let _m = mock("GET", "/api/v3/time")
+            .with_status(200)
+            .with_body(r#"{"serverTime": 1499827319559}"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        assert_eq!(client.server_time().unwrap(), 1499827319559);
+    }
+
+    #[test]
+    fn test_agg_trades_sends_unique_correlation_id() {
+        const HEADER: &str = "X-Correlation-Id";
+        let _m = server_mock_builder(400, "Bad Request")
+            .match_header(HEADER, Matcher::Regex("^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[0-9a-f]{4}-[0-9a-f]{12}$".to_string()))
+            .expect(2)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_correlation_header(HEADER);
+        let correlation_id = || {
+            let err = client.agg_trades("ETHUSDT", None, Some(100), Some(500), None).unwrap_err();
+            format!("{:#}", err).split_whitespace().nth(1).unwrap().to_string()
+        };
+        let first = correlation_id();
+        let second = correlation_id();
+
+        assert!(uuid::Uuid::parse_str(&first).is_ok());
+        assert!(uuid::Uuid::parse_str(&second).is_ok());
+        assert_ne!(first, second);
+        _m.assert();
+    }
+
+    #[test]
+    fn test_requests_send_user_agent_and_extra_headers() {
+        let _m = mock("GET", "/api/v3/time")
+            .match_header("User-Agent", DEFAULT_USER_AGENT)
+            .match_header("X-Extra", "extra-value")
+            .with_status(200)
+            .with_body(r#"{"serverTime": 1499827319559}"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_header("X-Extra", "extra-value").unwrap();
+
+        assert!(DEFAULT_USER_AGENT.starts_with("rust_practice/"));
+        assert_eq!(client.server_time().unwrap(), 1499827319559);
+        assert!(BinanceHttpClient::new().with_header("Bad Header", "value").is_err());
+    }
+
+    #[test]
+    fn test_with_proxy_validates_url() {
+        assert!(BinanceHttpClient::new().with_proxy("http://proxy.example.com:3128").is_ok());
+        assert!(BinanceHttpClient::new().with_proxy("socks5://127.0.0.1:1080").is_ok());
+
+        let err = BinanceHttpClient::new().with_proxy("http://proxy example:port").err().unwrap();
+        assert!(err.to_string().contains("Invalid proxy url"));
+    }
+
+    #[test]
+    fn test_with_user_agent_replaces_default() {
+        let _m = mock("GET", "/api/v3/time")
+            .match_header("User-Agent", "my-bot/1.0")
+            .with_status(200)
+            .with_body(r#"{"serverTime": 1499827319559}"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_user_agent("my-bot/1.0");
+        assert_eq!(client.server_time().unwrap(), 1499827319559);
+    }
+
+    #[test]
+    fn test_signed_query_matches_binance_example() {
+        // Example from Binance's "SIGNED endpoint security" docs
+        const SECRET_KEY: &str = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+        let params = [("symbol", "LTCBTC"), ("side", "BUY"), ("type", "LIMIT"), ("timeInForce", "GTC"),
+            ("quantity", "1"), ("price", "0.1"), ("recvWindow", "5000")];
+
+        assert_eq!(
+            signed_query(SECRET_KEY, &params, 1499827319559),
+            "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559\
+                &signature=c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+
+    #[test]
+    fn test_signed_get_sends_api_key_and_signature() {
+        let _m = mock("GET", "/api/v3/account")
+            .match_header(API_KEY_HEADER, API_KEY)
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("omitZeroBalances".into(), "true".into()),
+                Matcher::Regex("timestamp=[0-9]+".into()),
+                Matcher::Regex("signature=[0-9a-f]{64}$".into()),
+            ]))
+            .with_status(200)
+            .with_body("{}")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_api_key(API_KEY).with_secret_key("secret");
+
+        assert_eq!(client.signed_get("account", &[("omitZeroBalances", "true")]).unwrap(), "{}");
+        assert!(BinanceHttpClient::new_with_test_endpoint().with_api_key(API_KEY).signed_get("account", &[]).is_err());
+    }
+
+    /// Serves `responses` in order over a local socket, closing the connection after each
+    /// one, and returns the request lines received.
+    fn record_requests(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut request_lines = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                request_lines.push(line.trim_end().to_string());
+                while line != "\r\n" {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                (&stream).write_all(response.as_bytes()).unwrap();
+            }
+            request_lines
+        });
+        (base_url, server)
+    }
+
+    fn query_param(request_line: &str, name: &str) -> Option<String> {
+        let query = request_line.split_whitespace().nth(1)?.split_once('?')?.1;
+        query.split('&')
+            .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+            .map(str::to_string)
+    }
+
+    #[test]
+    fn test_signed_get_signs_each_attempt_with_offset_and_recv_window() {
+        const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+        let (base_url, server) = record_requests(vec![UNAVAILABLE, OK]);
+        let client = BinanceHttpClient::with_base_url(&base_url)
+            .with_retry_policy(RetryPolicy { max_retries: 1, backoff: Arc::new(FixedBackoff(Duration::from_millis(20))) })
+            .with_api_key(API_KEY)
+            .with_secret_key("secret")
+            .with_recv_window(Duration::from_secs(2));
+        client.set_clock_offset(chrono::Duration::hours(1));
+        let sent = chrono::Utc::now();
+
+        assert_eq!(client.signed_get("account", &[]).unwrap(), "{}");
+
+        let request_lines = server.join().unwrap();
+        let timestamps: Vec<i64> = request_lines.iter()
+            .map(|line| query_param(line, "timestamp").unwrap().parse().unwrap())
+            .collect();
+        assert!(timestamps[1] > timestamps[0], "{:?}", timestamps);
+        assert!(timestamps[0] >= (sent + chrono::Duration::hours(1)).timestamp_millis());
+        assert_ne!(query_param(&request_lines[0], "signature"), query_param(&request_lines[1], "signature"));
+        assert_eq!(query_param(&request_lines[1], "recvWindow").as_deref(), Some("2000"));
+    }
+
+    const API_KEY: &str = "test-api-key";
+    const LISTEN_KEY: &str = "pqia91ma19a5s61cv6a81va65sdf19v8a65a1a5s61cv6a81va65sdf19v8a65a1";
+
+    #[test]
+    fn test_create_listen_key_success() {
+        let _m = mock("POST", "/api/v3/userDataStream")
+            .match_header(API_KEY_HEADER, API_KEY)
+            .with_status(200)
+            .with_body(format!(r#"{{"listenKey": "{}"}}"#, LISTEN_KEY))
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_api_key(API_KEY);
+        assert_eq!(client.create_listen_key().unwrap(), LISTEN_KEY);
+        _m.assert();
+    }
+
+    #[test]
+    fn test_listen_key_requests_are_retried_and_counted() {
+        const HEADER: &str = "X-Correlation-Id";
+        let _m_unavailable = mock("POST", "/api/v3/userDataStream")
+            .match_header(HEADER, Matcher::Any)
+            .with_status(503)
+            .expect(1)
+            .create();
+        let _m_created = mock("POST", "/api/v3/userDataStream")
+            .match_header(HEADER, Matcher::Any)
+            .with_status(200)
+            .with_body(format!(r#"{{"listenKey": "{}"}}"#, LISTEN_KEY))
+            .create();
+        let _m_keepalive = mock("PUT", "/api/v3/userDataStream")
+            .match_header(HEADER, Matcher::Any)
+            .match_query(Matcher::UrlEncoded("listenKey".into(), LISTEN_KEY.into()))
+            .with_status(200)
+            .with_body("{}")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint()
+            .with_api_key(API_KEY)
+            .with_correlation_header(HEADER)
+            .with_retry_policy(RetryPolicy { max_retries: 1, backoff: Arc::new(FixedBackoff(Duration::from_millis(1))) });
+        assert_eq!(client.create_listen_key().unwrap(), LISTEN_KEY);
+        client.keepalive_listen_key(LISTEN_KEY).unwrap();
+
+        assert_eq!(client.metrics(), ClientMetrics { requests_total: 2, requests_failed: 0, retries_total: 1 });
+        _m_unavailable.assert();
+        _m_created.assert();
+        _m_keepalive.assert();
+    }
+
+    #[test]
+    fn test_create_listen_key_requires_api_key() {
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        assert!(client.create_listen_key().is_err());
+    }
+
+    #[test]
+    fn test_keepalive_listen_key_success() {
+        let _m = mock("PUT", "/api/v3/userDataStream")
+            .match_header(API_KEY_HEADER, API_KEY)
+            .match_query(Matcher::UrlEncoded("listenKey".into(), LISTEN_KEY.into()))
+            .with_status(200)
+            .with_body("{}")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_api_key(API_KEY);
+        assert!(client.keepalive_listen_key(LISTEN_KEY).is_ok());
+        _m.assert();
+    }
+
+    #[test]
+    fn test_exchange_info_success() {
+        let _m = mock("GET", "/api/v3/exchangeInfo")
+            .with_status(200)
+            .with_body(r#"{"symbols": [{"symbol": "ETHBTC", "status": "TRADING"}]}"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let response: ExchangeInfoResponse = serde_json::from_str(&client.exchange_info().unwrap()).unwrap();
+        assert_eq!(response.symbols.len(), 1);
+        assert_eq!(response.symbols[0].symbol, "ETHBTC");
+    }
+
+    #[test]
+    fn test_klines_success() {
+        let _m = mock("GET", "/api/v3/klines")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("symbol".into(), "ETHUSDT".into()),
+                Matcher::UrlEncoded("interval".into(), "1m".into()),
+                Matcher::UrlEncoded("startTime".into(), "100".into()),
+                Matcher::UrlEncoded("endTime".into(), "500".into()),
+            ]))
+            .with_status(200)
+            .with_body("a response")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let result = client.klines("ETHUSDT", "1m", Some(100), Some(500), None);
+        assert_eq!(result.unwrap(), "a response");
+    }
+
+    #[test]
+    fn test_klines_rejects_limit_over_max() {
+        let client = BinanceHttpClient::with_base_url("http://127.0.0.1:1");
+        let result = client.klines("ETHUSDT", "1m", Some(100), Some(500), Some(1001));
+        assert!(result.unwrap_err().to_string().contains("Invalid limit"));
+    }
+
+    #[test]
+    fn test_klines_response_decodes_positional_arrays() {
+        let response = r#"[[1499040000000,"0.01634790","0.80000000","0.01575800","0.01577100","148976.11427815",1499644799999,"2434.19055334",308,"1756.87402397","28.46694368","0"]]"#;
+        let klines: KlinesResponse = serde_json::from_str(response).unwrap();
+
+        assert_eq!(klines.len(), 1);
+        assert_eq!(klines[0].open_time, 1499040000000);
+        assert_eq!(klines[0].close, "0.01577100");
+        assert_eq!(klines[0].volume, "148976.11427815");
+        assert_eq!(klines[0].close_time, 1499644799999);
+        assert_eq!(klines[0].number_of_trades, 308);
+    }
+
+    fn delays(backoff: &dyn BackoffStrategy) -> Vec<Duration> {
+        (1..=4).map(|attempt| backoff.delay(attempt)).collect()
+    }
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    #[test]
+    fn test_fixed_backoff_delays() {
+        assert_eq!(delays(&FixedBackoff(Duration::from_millis(100))), millis(&[100, 100, 100, 100]));
+    }
+
+    #[test]
+    fn test_linear_backoff_delays() {
+        let backoff = LinearBackoff { step: Duration::from_millis(100) };
+        assert_eq!(delays(&backoff), millis(&[100, 200, 300, 400]));
+    }
+
+    #[test]
+    fn test_exponential_backoff_delays() {
+        let backoff = ExponentialBackoff { base: Duration::from_millis(100), jitter: false };
+        assert_eq!(delays(&backoff), millis(&[100, 200, 400, 800]));
+    }
+
+    #[test]
+    fn test_exponential_backoff_jitter_stays_within_base() {
+        let backoff = ExponentialBackoff { base: Duration::from_millis(100), jitter: true };
+        for (delay, expected) in delays(&backoff).into_iter().zip(millis(&[100, 200, 400, 800])) {
+            assert!(delay >= expected && delay <= expected + Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_backoff_delays_within_bounds() {
+        let backoff = DecorrelatedJitterBackoff { base: Duration::from_millis(100), cap: Duration::from_millis(2000) };
+        for (delay, upper) in delays(&backoff).into_iter().zip(millis(&[100, 300, 900, 2000])) {
+            assert!(delay >= Duration::from_millis(100) && delay <= upper);
+        }
+    }
+
+    /// Records the attempts it was asked to compute a delay for
+    #[derive(Debug, Default)]
+    struct RecordingBackoff(Mutex<Vec<u32>>);
+
+    impl BackoffStrategy for RecordingBackoff {
+        fn delay(&self, attempt: u32) -> Duration {
+            self.0.lock().unwrap().push(attempt);
+            Duration::ZERO
+        }
+    }
+
+    #[test]
+    fn test_agg_trades_uses_configured_backoff() {
+        let _m = server_mock_builder(500, "Internal Server Error").expect(3).create();
+
+        let backoff = Arc::new(RecordingBackoff::default());
+        let client = BinanceHttpClient::new_with_test_endpoint()
+            .with_retry_policy(RetryPolicy { max_retries: 2, backoff: backoff.clone() });
+        let result = client.agg_trades("ETHUSDT", None, Some(100), Some(500), None);
+
+        assert!(result.is_err());
+        assert_eq!(*backoff.0.lock().unwrap(), vec![1, 2]);
+        _m.assert();
+    }
+
+    #[test]
+    fn test_with_base_url_sends_requests_to_base() {
+        let _m = mock("GET", "/api/v3/aggTrades")
+            .match_query(Matcher::UrlEncoded("symbol".into(), "ETHUSDT".into()))
+            .with_status(200)
+            .with_body("[]")
+            .expect(1)
+            .create();
+
+        let client = BinanceHttpClient::with_base_url(&format!("{}/", mockito::server_url()));
+        let result = client.agg_trades("ETHUSDT", None, None, None, None);
+
+        assert_eq!(result.unwrap(), "[]");
+        _m.assert();
+    }
+
+    #[test]
+    fn test_ticker_24hr_decodes_response() {
+        let _m = mock("GET", "/api/v3/ticker/24hr")
+            .match_query(Matcher::UrlEncoded("symbol".into(), "BNBBTC".into()))
+            .with_status(200)
+            .with_body(r#"{
+                "symbol": "BNBBTC", "priceChange": "-94.99999800", "priceChangePercent": "-95.960",
+                "weightedAvgPrice": "0.29628482", "prevClosePrice": "0.10002000", "lastPrice": "4.00000200",
+                "lastQty": "200.00000000", "bidPrice": "4.00000000", "askPrice": "4.00000200",
+                "openPrice": "99.00000000", "highPrice": "100.00000000", "lowPrice": "0.10000000",
+                "volume": "8913.30000000", "quoteVolume": "15.30000000", "openTime": 1499783499040,
+                "closeTime": 1499869899040, "firstId": 28385, "lastId": 28460, "count": 76
+            }"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let ticker: Ticker24hr = serde_json::from_str(&client.ticker_24hr("BNBBTC").unwrap()).unwrap();
+
+        assert_eq!(ticker.last_price, "4.00000200");
+        assert_eq!(ticker.price_change_percent, "-95.960");
+        assert_eq!(ticker.high_price, "100.00000000");
+        assert_eq!(ticker.low_price, "0.10000000");
+        assert_eq!(ticker.volume, "8913.30000000");
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trade_parses_price_and_quantity() {
+        let trades: AggTrades = serde_json::from_str(
+            r#"[{"a": 26129,"p": "0.01633102","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#
+        ).unwrap();
+
+        assert_eq!(trades[0].id, 26129);
+        assert_eq!(trades[0].price, Decimal::from_str("0.01633102").unwrap());
+        assert_eq!(trades[0].quantity, Decimal::from_str("4.70443515").unwrap());
+        assert_eq!(trades[0].time, 1498793709153);
+    }
+
+    #[test]
+    fn test_agg_trade_error_names_field_and_value() {
+        let err = serde_json::from_str::<AggTrades>(
+            r#"[{"a": 26129,"p": "0.01633102","q": "notafloat","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#
+        ).unwrap_err();
+
+        assert!(err.to_string().contains(r#"invalid number "notafloat" in field `q`"#), "{}", err);
+    }
+
+    #[test]
+    fn test_depth_decodes_order_book() {
+        let _m = mock("GET", "/api/v3/depth")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("symbol".into(), "BNBBTC".into()),
+                Matcher::UrlEncoded("limit".into(), "5".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"lastUpdateId": 1027024, "bids": [["4.00000000", "431.00000000"]], "asks": [["4.00000200", "12.00000000"]]}"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let order_book: OrderBook = serde_json::from_str(&client.depth("BNBBTC", Some(5)).unwrap()).unwrap();
+
+        assert_eq!(order_book, OrderBook { bids: vec![(4.0, 431.0)], asks: vec![(4.000002, 12.0)] });
+        _m.assert();
+    }
+
+    #[test]
+    fn test_avg_price_decodes_mins_and_price() {
+        let _m = mock("GET", "/api/v3/avgPrice")
+            .match_query(Matcher::UrlEncoded("symbol".into(), "BNBBTC".into()))
+            .with_status(200)
+            .with_body(r#"{"mins": 5, "price": "9.35751834", "closeTime": 1694061154503}"#)
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let avg_price: AvgPrice = serde_json::from_str(&client.avg_price("BNBBTC").unwrap()).unwrap();
+
+        assert_eq!(avg_price, AvgPrice { mins: 5, price: "9.35751834".to_string() });
+        _m.assert();
+    }
+
+    #[test]
+    fn test_order_book_rejects_non_numeric_level() {
+        let result = serde_json::from_str::<OrderBook>(r#"{"bids": [["notafloat", "1.0"]], "asks": []}"#);
+        assert!(result.unwrap_err().to_string().contains("invalid order book level"));
+    }
+
+    fn gzip(body: &str) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_agg_trades_decompresses_gzip_response() {
+        let body = r#"[{"a": 26129,"p": "0.01633102","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
+        let _m = mock("GET", "/api/v3/aggTrades")
+            .match_query(Matcher::Any)
+            .match_header("accept-encoding", Matcher::Regex("gzip".to_string()))
+            .with_status(200)
+            .with_header("content-encoding", "gzip")
+            .with_body(gzip(body))
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint();
+        let response = client.agg_trades("ETHUSDT", None, None, None, None).unwrap();
+
+        assert_eq!(response, body);
+        let trades: AggTradesResponse = serde_json::from_str(&response).unwrap();
+        assert_eq!(trades[0].p, "0.01633102");
+        _m.assert();
+    }
+
+    #[test]
+    fn test_agg_trades_without_compression_does_not_ask_for_gzip() {
+        let _m = mock("GET", "/api/v3/aggTrades")
+            .match_query(Matcher::Any)
+            .match_header("accept-encoding", Matcher::Missing)
+            .with_status(200)
+            .with_body("[]")
+            .create();
+
+        let client = BinanceHttpClient::new_with_test_endpoint().with_compression(false);
+
+        assert_eq!(client.agg_trades("ETHUSDT", None, None, None, None).unwrap(), "[]");
+        _m.assert();
+    }
+}
@@ -0,0 +1,195 @@
+use super::binance_api::{BackoffStrategy, ExponentialBackoff};
+use crate::price_providers::{PriceError, PricePoint};
+use anyhow::Context;
+use chrono::DateTime;
+use futures_util::{Stream, StreamExt};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Message pushed by the `{symbol}@aggTrade` stream, only the fields we use.
+///
+/// ```json
+/// {
+///   "e": "aggTrade",    // Event type
+///   "E": 1672515782136, // Event time
+///   "s": "BNBBTC",      // Symbol
+///   "a": 12345,         // Aggregate trade ID
+///   "p": "0.001",       // Price
+///   "q": "100",         // Quantity
+///   "f": 100,           // First trade ID
+///   "l": 105,           // Last trade ID
+///   "T": 1672515782136, // Trade time
+///   "m": true,          // Is the buyer the market maker?
+///   "M": true           // Ignore
+/// }
+/// ```
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct AggTradeEvent {
+    p: String,
+    T: i64,
+}
+
+/// Turns a streamed aggTrade message into a `PricePoint` at the trade time.
+pub fn parse_agg_trade(message: &str) -> anyhow::Result<PricePoint> {
+    let event: AggTradeEvent = serde_json::from_str(message).map_err(PriceError::Decode)?;
+    let timestamp = DateTime::from_timestamp_millis(event.T)
+        .with_context(|| format!("Invalid trade time {}", event.T))?;
+    let price = Decimal::from_str(&event.p).map_err(|_| PriceError::InvalidPrice(event.p.clone()))?;
+    Ok(PricePoint { timestamp, price })
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+struct Subscription {
+    url: String,
+    backoff: Arc<dyn BackoffStrategy + Send + Sync>,
+    socket: Option<Socket>,
+    /// Consecutive failed connections, 0 while connected.
+    attempt: u32,
+}
+
+/// Live aggregate trades from the Binance WebSocket streams.
+pub struct BinanceStream {
+    base_url: String,
+    backoff: Arc<dyn BackoffStrategy + Send + Sync>,
+}
+
+impl BinanceStream {
+    pub fn new() -> Self {
+        Self::with_base_url("wss://stream.binance.com:9443")
+    }
+
+    /// See `BinanceHttpClient::with_base_url`, e.g. `wss://stream.testnet.binance.vision`.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            backoff: Arc::new(ExponentialBackoff { base: Duration::from_millis(200), jitter: true }),
+        }
+    }
+
+    /// How long to wait before reconnecting, by number of consecutive failures.
+    pub fn with_backoff(mut self, backoff: Arc<dyn BackoffStrategy + Send + Sync>) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Prices of every aggregate trade of `symbol` as they happen.
+    ///
+    /// The stream never ends: when the socket closes or fails (Binance drops connections
+    /// after 24h) it reconnects after the backoff delay. Connection and decoding errors
+    /// are yielded as `Err` items so callers can log them and keep reading.
+    pub fn subscribe(&self, symbol: &str) -> impl Stream<Item = anyhow::Result<PricePoint>> + Send {
+        let subscription = Subscription {
+            url: format!("{}/ws/{}@aggTrade", self.base_url, symbol.to_lowercase()),
+            backoff: self.backoff.clone(),
+            socket: None,
+            attempt: 0,
+        };
+        futures_util::stream::unfold(subscription, |mut subscription| async move {
+            loop {
+                let socket = match subscription.socket.as_mut() {
+                    Some(socket) => socket,
+                    None => {
+                        if subscription.attempt > 0 {
+                            tokio::time::sleep(subscription.backoff.delay(subscription.attempt)).await;
+                        }
+                        match tokio_tungstenite::connect_async(subscription.url.as_str()).await {
+                            Ok((socket, _)) => {
+                                subscription.attempt = 0;
+                                subscription.socket.insert(socket)
+                            }
+                            Err(err) => {
+                                subscription.attempt += 1;
+                                let err = anyhow::Error::new(err).context(format!("Failed to connect to {}", subscription.url));
+                                return Some((Err(err), subscription));
+                            }
+                        }
+                    }
+                };
+                match socket.next().await {
+                    Some(Ok(Message::Text(text))) => return Some((parse_agg_trade(text.as_str()), subscription)),
+                    Some(Ok(Message::Close(_))) | None => {
+                        subscription.socket = None;
+                        subscription.attempt = 1;
+                    }
+                    // Pings are answered by tungstenite
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => {
+                        subscription.socket = None;
+                        subscription.attempt = 1;
+                        return Some((Err(anyhow::Error::new(err).context("WebSocket stream failed")), subscription));
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Default for BinanceStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::binance_api::FixedBackoff;
+    use futures_util::SinkExt;
+    use rust_decimal_macros::dec;
+
+    const AGG_TRADE_FRAMES: [&str; 2] = [
+        r#"{"e":"aggTrade","E":1737986400100,"s":"BTCUSDC","a":1,"p":"101000.50","q":"0.1","f":1,"l":1,"T":1737986400000,"m":true,"M":true}"#,
+        r#"{"e":"aggTrade","E":1737986401100,"s":"BTCUSDC","a":2,"p":"101001.25","q":"0.2","f":2,"l":3,"T":1737986401000,"m":false,"M":true}"#,
+    ];
+
+    #[test]
+    fn test_parse_agg_trade_uses_trade_time_and_price() {
+        let points: Vec<PricePoint> = AGG_TRADE_FRAMES.iter().map(|frame| parse_agg_trade(frame).unwrap()).collect();
+
+        assert_eq!( points[0].timestamp, DateTime::from_timestamp_millis(1737986400000).unwrap() );
+        assert_eq!( points[0].price, dec!(101000.50) );
+        assert_eq!( points[1].timestamp, DateTime::from_timestamp_millis(1737986401000).unwrap() );
+        assert_eq!( points[1].price, dec!(101001.25) );
+    }
+
+    #[test]
+    fn test_parse_agg_trade_rejects_invalid_messages() {
+        assert!( parse_agg_trade(r#"{"result":null,"id":1}"#).is_err() );
+        assert!( parse_agg_trade(r#"{"p":"notafloat","T":1737986400000}"#).is_err() );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_reconnects_when_the_socket_closes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Each connection gets one frame and is then closed by the server
+        tokio::spawn(async move {
+            for frame in AGG_TRADE_FRAMES {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                socket.send(Message::text(frame)).await.unwrap();
+                socket.close(None).await.unwrap();
+            }
+        });
+
+        let stream = BinanceStream::with_base_url(&format!("ws://{}", addr))
+            .with_backoff(Arc::new(FixedBackoff(Duration::from_millis(1))));
+        let points: Vec<PricePoint> = stream.subscribe("BTCUSDC")
+            .take(2)
+            .map(|point| point.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!( points.len(), 2 );
+        assert_eq!( points[0].price, dec!(101000.50) );
+        assert_eq!( points[1].price, dec!(101001.25) );
+    }
+}
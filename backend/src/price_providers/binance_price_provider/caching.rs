@@ -0,0 +1,295 @@
+use super::binance_api::BinanceAPI;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long aggTrades responses are reused by default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5);
+/// Default lifetime of responses for windows that ended at least `HISTORICAL_AGE` ago.
+pub const DEFAULT_HISTORICAL_TTL: Duration = Duration::from_secs(3600);
+/// Default bound on the size of the cached response bodies.
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+/// Age past which a window's trades are considered final.
+const HISTORICAL_AGE: chrono::Duration = chrono::Duration::minutes(5);
+
+/// `(symbol, start_time, end_time, limit, from_id)` of an aggTrades call.
+type Key = (String, Option<i64>, Option<i64>, Option<i64>, Option<i64>);
+
+struct Entry {
+    response: String,
+    expires: Instant,
+    /// Tick of the last use, its key in `Entries::by_use`.
+    used: u64,
+    /// Tick of the insert, with `expires` its key in `Entries::by_expiry`.
+    inserted: u64,
+}
+
+/// Cached responses with the order they were last used in and the order they expire in,
+/// so the least recently used and the expired ones can be evicted without scanning.
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<Key, Entry>,
+    by_use: BTreeMap<u64, Key>,
+    by_expiry: BTreeMap<(Instant, u64), Key>,
+    tick: u64,
+    bytes: usize,
+}
+
+impl Entries {
+    /// The response for `key` unless expired, marking it as just used.
+    fn get(&mut self, key: &Key, now: Instant) -> Option<String> {
+        let entry = self.entries.get_mut(key).filter(|entry| now < entry.expires)?;
+        self.by_use.remove(&entry.used);
+        self.tick += 1;
+        entry.used = self.tick;
+        self.by_use.insert(self.tick, key.clone());
+        Some(entry.response.clone())
+    }
+
+    /// Adds `response` after dropping expired entries, then least recently used ones
+    /// until everything fits in `max_bytes`. A response larger than that isn't kept.
+    fn insert(&mut self, key: Key, response: String, expires: Instant, now: Instant, max_bytes: usize) {
+        self.remove(&key);
+        if response.len() > max_bytes {
+            return;
+        }
+        while let Some(entry) = self.by_expiry.first_entry().filter(|entry| now >= entry.key().0) {
+            let expired = entry.remove();
+            self.remove(&expired);
+        }
+        while self.bytes + response.len() > max_bytes {
+            let Some((_, oldest)) = self.by_use.pop_first() else { break };
+            self.remove(&oldest);
+        }
+        self.tick += 1;
+        self.bytes += response.len();
+        self.by_use.insert(self.tick, key.clone());
+        self.by_expiry.insert((expires, self.tick), key.clone());
+        self.entries.insert(key, Entry { response, expires, used: self.tick, inserted: self.tick });
+    }
+
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.by_use.remove(&entry.used);
+            self.by_expiry.remove(&(entry.expires, entry.inserted));
+            self.bytes -= entry.response.len();
+        }
+    }
+}
+
+/// `BinanceAPI` memoizing the aggTrades responses of the wrapped one.
+///
+/// Responses are reused for `ttl`, or for `historical_ttl` when the requested window
+/// ended long enough ago that its trades can't change anymore. Only successful responses
+/// are kept and expired entries are evicted on every insert, as are the least recently
+/// used ones once the bodies exceed `max_bytes`. Other calls go straight through.
+pub struct CachingBinanceAPI {
+    inner: Box<dyn BinanceAPI + Send + Sync>,
+    ttl: Duration,
+    historical_ttl: Duration,
+    max_bytes: usize,
+    entries: Mutex<Entries>,
+}
+
+impl CachingBinanceAPI {
+    pub fn new(inner: Box<dyn BinanceAPI + Send + Sync>) -> Self {
+        Self {
+            inner,
+            ttl: DEFAULT_TTL,
+            historical_ttl: DEFAULT_HISTORICAL_TTL,
+            max_bytes: DEFAULT_MAX_BYTES,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// How long responses are reused, `DEFAULT_TTL` by default.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// How long responses for windows in the past are reused, `DEFAULT_HISTORICAL_TTL` by default.
+    pub fn with_historical_ttl(mut self, historical_ttl: Duration) -> Self {
+        self.historical_ttl = historical_ttl;
+        self
+    }
+
+    /// Most bytes of response bodies kept at once, `DEFAULT_MAX_BYTES` by default.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    fn ttl_for(&self, end_time: Option<i64>) -> Duration {
+        let historical_before = (chrono::Utc::now() - HISTORICAL_AGE).timestamp_millis();
+        match end_time {
+            Some(end_time) if end_time < historical_before => self.historical_ttl,
+            _ => self.ttl,
+        }
+    }
+}
+
+impl BinanceAPI for CachingBinanceAPI {
+    fn agg_trades(&self,
+        symbol: &str,
+        from_id: Option<i64>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String> {
+        let key = (symbol.to_string(), start_time, end_time, limit, from_id);
+        if let Some(response) = self.entries.lock().unwrap().get(&key, Instant::now()) {
+            return Ok(response);
+        }
+
+        // Not holding the lock while fetching, concurrent misses may fetch the same window twice
+        let response = self.inner.agg_trades(symbol, from_id, start_time, end_time, limit)?;
+        let now = Instant::now();
+        let expires = now + self.ttl_for(end_time);
+        self.entries.lock().unwrap().insert(key, response.clone(), expires, now, self.max_bytes);
+        Ok(response)
+    }
+
+    fn create_listen_key(&self) -> anyhow::Result<String> {
+        self.inner.create_listen_key()
+    }
+
+    fn keepalive_listen_key(&self, listen_key: &str) -> anyhow::Result<()> {
+        self.inner.keepalive_listen_key(listen_key)
+    }
+
+    fn exchange_info(&self) -> anyhow::Result<String> {
+        self.inner.exchange_info()
+    }
+
+    fn klines(&self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String> {
+        self.inner.klines(symbol, interval, start_time, end_time, limit)
+    }
+
+    fn ticker_24hr(&self, symbol: &str) -> anyhow::Result<String> {
+        self.inner.ticker_24hr(symbol)
+    }
+
+    fn server_time(&self) -> anyhow::Result<i64> {
+        self.inner.server_time()
+    }
+
+    fn ping(&self) -> anyhow::Result<()> {
+        self.inner.ping()
+    }
+
+    fn depth(&self, symbol: &str, limit: Option<i64>) -> anyhow::Result<String> {
+        self.inner.depth(symbol, limit)
+    }
+
+    fn avg_price(&self, symbol: &str) -> anyhow::Result<String> {
+        self.inner.avg_price(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_binance_api::MockBinanceAPI;
+
+    /// Start and end millis of a recent window, cached for the short ttl
+    fn recent_window() -> (i64, i64) {
+        let end = chrono::Utc::now().timestamp_millis();
+        (end - 60_000, end)
+    }
+
+    fn counting_mock(times: usize) -> MockBinanceAPI {
+        let mut mock_api = MockBinanceAPI::new();
+        let mut calls = 0;
+        mock_api.expect_agg_trades()
+            .times(times)
+            .returning(move |_,_,_,_,_| {
+                calls += 1;
+                Ok(format!("response {}", calls))
+            });
+        mock_api
+    }
+
+    #[test]
+    fn test_caching_api_reuses_response_within_ttl() {
+        let api = CachingBinanceAPI::new(Box::new(counting_mock(1)));
+        let (start, end) = recent_window();
+
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+    }
+
+    #[test]
+    fn test_caching_api_fetches_other_ranges_and_symbols() {
+        let api = CachingBinanceAPI::new(Box::new(counting_mock(3)));
+        let (start, end) = recent_window();
+
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start + 1), Some(end), None).unwrap(), "response 2");
+        assert_eq!(api.agg_trades("ETHUSDC", None, Some(start), Some(end), None).unwrap(), "response 3");
+    }
+
+    #[test]
+    fn test_caching_api_refetches_after_ttl_unless_historical() {
+        let api = CachingBinanceAPI::new(Box::new(counting_mock(3))).with_ttl(Duration::ZERO);
+        let (start, end) = recent_window();
+
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 2");
+        // A window of 2017, answered from the cache for the historical ttl
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(1498793700000), Some(1498793759999), None).unwrap(), "response 3");
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(1498793700000), Some(1498793759999), None).unwrap(), "response 3");
+    }
+
+    #[test]
+    fn test_caching_api_evicts_least_recently_used_past_max_bytes() {
+        // Room for two of the 10 byte responses
+        let api = CachingBinanceAPI::new(Box::new(counting_mock(4))).with_max_bytes(25);
+        let (start, end) = recent_window();
+
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+        assert_eq!(api.agg_trades("ETHUSDC", None, Some(start), Some(end), None).unwrap(), "response 2");
+        // BTCUSDC is now the most recently used, ETHUSDC goes first
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+        assert_eq!(api.agg_trades("SOLUSDC", None, Some(start), Some(end), None).unwrap(), "response 3");
+
+        assert_eq!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap(), "response 1");
+        assert_eq!(api.agg_trades("ETHUSDC", None, Some(start), Some(end), None).unwrap(), "response 4");
+        assert!(api.entries.lock().unwrap().bytes <= 25);
+    }
+
+    #[test]
+    fn test_caching_api_evicts_expired_entries_on_insert() {
+        let api = CachingBinanceAPI::new(Box::new(counting_mock(3))).with_ttl(Duration::ZERO);
+        let (start, end) = recent_window();
+
+        api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).unwrap();
+        api.agg_trades("ETHUSDC", None, Some(start), Some(end), None).unwrap();
+        api.agg_trades("SOLUSDC", None, Some(start), Some(end), None).unwrap();
+
+        let entries = api.entries.lock().unwrap();
+        assert_eq!(entries.entries.len(), 1);
+        assert_eq!(entries.by_use.len(), 1);
+        assert_eq!(entries.by_expiry.len(), 1);
+        assert_eq!(entries.bytes, "response 3".len());
+    }
+
+    #[test]
+    fn test_caching_api_does_not_cache_errors() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(2)
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("connection refused")));
+        let api = CachingBinanceAPI::new(Box::new(mock_api));
+        let (start, end) = recent_window();
+
+        assert!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).is_err());
+        assert!(api.agg_trades("BTCUSDC", None, Some(start), Some(end), None).is_err());
+    }
+}
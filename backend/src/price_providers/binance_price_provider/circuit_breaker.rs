@@ -0,0 +1,271 @@
+use super::binance_api::BinanceAPI;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive failures that open the circuit by default.
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+/// Default time the circuit stays open before a call may test recovery.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Returned instead of calling Binance while the circuit is open.
+#[derive(Debug, thiserror::Error)]
+#[error("Circuit open after repeated Binance failures, next attempt allowed in {retry_in:?}")]
+pub struct CircuitOpen {
+    pub retry_in: Duration,
+}
+
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// The cooldown is over and a single trial call is in flight.
+    HalfOpen,
+}
+
+/// `BinanceAPI` that stops calling the wrapped one during outages.
+///
+/// After `failure_threshold` consecutive failures the circuit opens and every call fails
+/// with `CircuitOpen` for `cooldown`. The next call after that is let through as a trial:
+/// success closes the circuit, failure opens it for another cooldown. Client errors
+/// (4xx other than 429) say nothing about Binance being down and don't count as failures.
+pub struct CircuitBreaker {
+    inner: Box<dyn BinanceAPI + Send + Sync>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: Box<dyn BinanceAPI + Send + Sync>) -> Self {
+        Self {
+            inner,
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            cooldown: DEFAULT_COOLDOWN,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Consecutive failures that open the circuit, `DEFAULT_FAILURE_THRESHOLD` by default.
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// How long the circuit stays open, `DEFAULT_COOLDOWN` by default.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Whether the circuit tripped and no call has succeeded since.
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.state.lock().unwrap(), State::Closed { .. })
+    }
+
+    fn call<T>(&self, call: impl FnOnce(&dyn BinanceAPI) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let trial = {
+            let mut state = self.state.lock().unwrap();
+            match *state {
+                State::Closed { .. } => false,
+                State::Open { until } => {
+                    let now = Instant::now();
+                    if now < until {
+                        return Err(CircuitOpen { retry_in: until - now }.into());
+                    }
+                    *state = State::HalfOpen;
+                    true
+                }
+                State::HalfOpen => return Err(CircuitOpen { retry_in: Duration::ZERO }.into()),
+            }
+        };
+
+        let guard = trial.then(|| TrialGuard { breaker: self });
+        let result = call(self.inner.as_ref());
+        std::mem::forget(guard);
+
+        let mut state = self.state.lock().unwrap();
+        match &result {
+            Err(err) if is_outage(err) => {
+                let failures = match *state {
+                    State::Closed { failures } if !trial => failures + 1,
+                    _ => self.failure_threshold,
+                };
+                *state = if failures >= self.failure_threshold {
+                    tracing::warn!(failures, cooldown = ?self.cooldown, "Opening circuit to Binance");
+                    State::Open { until: Instant::now() + self.cooldown }
+                } else {
+                    State::Closed { failures }
+                };
+            }
+            _ => *state = State::Closed { failures: 0 },
+        }
+        result
+    }
+}
+
+/// Opens the circuit for another cooldown if a trial call unwinds instead of returning,
+/// so the breaker doesn't stay half open and reject every later call.
+struct TrialGuard<'a> {
+    breaker: &'a CircuitBreaker,
+}
+
+impl Drop for TrialGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.breaker.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *state = State::Open { until: Instant::now() + self.breaker.cooldown };
+    }
+}
+
+/// Whether `err` may come from Binance being down, rather than from a bad request.
+fn is_outage(err: &anyhow::Error) -> bool {
+    let status = err.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .find_map(|cause| cause.status());
+    match status {
+        Some(status) => !status.is_client_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        None => true,
+    }
+}
+
+impl BinanceAPI for CircuitBreaker {
+    fn agg_trades(&self,
+        symbol: &str,
+        from_id: Option<i64>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String> {
+        self.call(|api| api.agg_trades(symbol, from_id, start_time, end_time, limit))
+    }
+
+    fn create_listen_key(&self) -> anyhow::Result<String> {
+        self.call(|api| api.create_listen_key())
+    }
+
+    fn keepalive_listen_key(&self, listen_key: &str) -> anyhow::Result<()> {
+        self.call(|api| api.keepalive_listen_key(listen_key))
+    }
+
+    fn exchange_info(&self) -> anyhow::Result<String> {
+        self.call(|api| api.exchange_info())
+    }
+
+    fn klines(&self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<String> {
+        self.call(|api| api.klines(symbol, interval, start_time, end_time, limit))
+    }
+
+    fn ticker_24hr(&self, symbol: &str) -> anyhow::Result<String> {
+        self.call(|api| api.ticker_24hr(symbol))
+    }
+
+    fn server_time(&self) -> anyhow::Result<i64> {
+        self.call(|api| api.server_time())
+    }
+
+    fn ping(&self) -> anyhow::Result<()> {
+        self.call(|api| api.ping())
+    }
+
+    fn depth(&self, symbol: &str, limit: Option<i64>) -> anyhow::Result<String> {
+        self.call(|api| api.depth(symbol, limit))
+    }
+
+    fn avg_price(&self, symbol: &str) -> anyhow::Result<String> {
+        self.call(|api| api.avg_price(symbol))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_binance_api::MockBinanceAPI;
+
+    const COOLDOWN: Duration = Duration::from_millis(50);
+
+    /// Breaker opening after 3 failures over a ping failing its first `failing_calls` calls
+    fn breaker(failing_calls: usize, total_calls: usize) -> CircuitBreaker {
+        let mut mock_api = MockBinanceAPI::new();
+        let mut calls = 0;
+        mock_api.expect_ping()
+            .times(total_calls)
+            .returning(move || {
+                calls += 1;
+                if calls <= failing_calls { Err(anyhow::Error::msg("connection refused")) } else { Ok(()) }
+            });
+        CircuitBreaker::new(Box::new(mock_api))
+            .with_failure_threshold(3)
+            .with_cooldown(COOLDOWN)
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures_then_recovers() {
+        let breaker = breaker(3, 4);
+
+        for _ in 0..3 {
+            assert!(breaker.ping().is_err());
+        }
+        assert!(breaker.is_open());
+        // Rejected without reaching the inner API, which only expects 4 calls
+        let err = breaker.ping().unwrap_err();
+        assert!(err.downcast_ref::<CircuitOpen>().is_some());
+
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.ping().is_ok());
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn test_failed_trial_reopens_circuit() {
+        let breaker = breaker(4, 4);
+
+        for _ in 0..3 {
+            assert!(breaker.ping().is_err());
+        }
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.ping().unwrap_err().downcast_ref::<CircuitOpen>().is_none());
+
+        assert!(breaker.ping().unwrap_err().downcast_ref::<CircuitOpen>().is_some());
+    }
+
+    #[test]
+    fn test_panicking_trial_reopens_circuit() {
+        let breaker = breaker(3, 4);
+
+        for _ in 0..3 {
+            assert!(breaker.ping().is_err());
+        }
+        std::thread::sleep(COOLDOWN);
+        let trial = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            breaker.call(|_| -> anyhow::Result<()> { panic!("trial panicked") })
+        }));
+        assert!(trial.is_err());
+
+        let err = breaker.ping().unwrap_err();
+        assert!(err.downcast_ref::<CircuitOpen>().unwrap().retry_in > Duration::ZERO);
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.ping().is_ok());
+    }
+
+    #[test]
+    fn test_successes_reset_the_failure_count() {
+        let mut mock_api = MockBinanceAPI::new();
+        let mut calls = 0;
+        mock_api.expect_ping()
+            .times(6)
+            .returning(move || {
+                calls += 1;
+                if calls % 3 == 0 { Ok(()) } else { Err(anyhow::Error::msg("connection refused")) }
+            });
+        let breaker = CircuitBreaker::new(Box::new(mock_api)).with_failure_threshold(3);
+
+        for _ in 0..6 {
+            let _ = breaker.ping();
+        }
+        assert!(!breaker.is_open());
+    }
+}
@@ -0,0 +1,28 @@
+use super::binance_api::BinanceAPI;
+use mockall::mock;
+
+mock! {
+    pub BinanceAPI {}
+    impl BinanceAPI for BinanceAPI {
+        fn agg_trades(&self, 
+                      symbol: &str,
+                      from_id: Option<i64>,
+                      start_time: Option<i64>,
+                      end_time: Option<i64>,
+                      limit: Option<i64>) -> anyhow::Result<String>;
+        fn create_listen_key(&self) -> anyhow::Result<String>;
+        fn keepalive_listen_key(&self, listen_key: &str) -> anyhow::Result<()>;
+        fn exchange_info(&self) -> anyhow::Result<String>;
+        fn klines(&self,
+                  symbol: &str,
+                  interval: &str,
+                  start_time: Option<i64>,
+                  end_time: Option<i64>,
+                  limit: Option<i64>) -> anyhow::Result<String>;
+        fn ticker_24hr(&self, symbol: &str) -> anyhow::Result<String>;
+        fn server_time(&self) -> anyhow::Result<i64>;
+        fn ping(&self) -> anyhow::Result<()>;
+        fn depth(&self, symbol: &str, limit: Option<i64>) -> anyhow::Result<String>;
+        fn avg_price(&self, symbol: &str) -> anyhow::Result<String>;
+    }
+}
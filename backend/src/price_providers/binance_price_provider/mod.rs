@@ -1 +1,10 @@
-pub mod binance_api;
\ No newline at end of file
+pub mod binance_api;
+pub mod caching;
+pub mod circuit_breaker;
+#[cfg(feature = "async")]
+pub mod async_binance_api;
+#[cfg(feature = "stream")]
+pub mod binance_stream;
+#[cfg(test)]
+pub mod mock_binance_api;
+pub mod rate_limiter;
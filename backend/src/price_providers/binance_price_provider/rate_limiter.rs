@@ -0,0 +1,93 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket throttling requests to stay within Binance's request weight budget.
+///
+/// Holds up to `capacity` tokens, refilled continuously at `refill_per_second`.
+/// Each request takes as many tokens as its weight. Share one limiter through an
+/// `Arc` between every client calling from the same IP.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// A full bucket of `capacity` tokens.
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        assert!(refill_per_second > 0.0, "Refill rate must be positive");
+        Self {
+            capacity: capacity as f64,
+            refill_per_second,
+            bucket: Mutex::new(Bucket { tokens: capacity as f64, last_refill: Instant::now() }),
+        }
+    }
+
+    /// Blocks until `weight` tokens are available and takes them.
+    /// A weight above the capacity waits for a full bucket instead of blocking forever.
+    pub fn acquire(&self, weight: u32) {
+        let weight = (weight as f64).min(self.capacity);
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let refilled = now.duration_since(bucket.last_refill).as_secs_f64() * self.refill_per_second;
+                bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+                bucket.last_refill = now;
+                if bucket.tokens >= weight {
+                    bucket.tokens -= weight;
+                    return;
+                }
+                Duration::from_secs_f64((weight - bucket.tokens) / self.refill_per_second)
+            };
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_acquire_waits_for_refill_once_drained() {
+        let limiter = RateLimiter::new(4, 20.0);
+        let started = Instant::now();
+        limiter.acquire(2);
+        limiter.acquire(2);
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        limiter.acquire(2);
+        // 2 tokens at 20 per second
+        assert!(started.elapsed() >= Duration::from_millis(95));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_acquire_is_shared_across_threads() {
+        let limiter = Arc::new(RateLimiter::new(2, 20.0));
+        let started = Instant::now();
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = limiter.clone();
+                std::thread::spawn(move || limiter.acquire(2))
+            })
+            .collect();
+        handles.into_iter().for_each(|handle| handle.join().unwrap());
+
+        // First call is free, the other two wait 100ms each
+        assert!(started.elapsed() >= Duration::from_millis(195));
+    }
+
+    #[test]
+    fn test_acquire_caps_weight_at_capacity() {
+        let limiter = RateLimiter::new(2, 1000.0);
+        limiter.acquire(5);
+    }
+}
@@ -0,0 +1,155 @@
+use super::{time_windows, WindowSize};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// How a range is split into the windows prices are averaged over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bucketing {
+    /// Windows of a fixed duration starting at the beginning of the range.
+    Fixed(WindowSize),
+    /// One window per calendar day in the given timezone.
+    /// Days start at local midnight, so DST transitions give 23 or 25 hour windows.
+    /// The first window starts at the beginning of the range.
+    DailyTz(Tz),
+}
+
+impl Bucketing {
+    pub fn windows<'a>(&self, start_time: &'a DateTime<Utc>, end_time: &'a DateTime<Utc>) -> Box<dyn Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> + Send + 'a> {
+        match *self {
+            Bucketing::Fixed(window) => Box::new(time_windows(start_time, end_time, window.as_duration())),
+            Bucketing::DailyTz(tz) => Box::new(daily_windows(start_time, end_time, tz)),
+        }
+    }
+
+    /// Number of windows `windows` yields for the range, computed without iterating.
+    /// For `DailyTz` it's an upper bound, days can be as short as 23 hours.
+    pub fn window_count(&self, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> i64 {
+        let span = (*end_time - *start_time).num_milliseconds();
+        let window = match self {
+            Bucketing::Fixed(window) => window.as_duration().num_milliseconds(),
+            Bucketing::DailyTz(_) => Duration::hours(23).num_milliseconds(),
+        };
+        // The first window is partial for DailyTz, it ends at the next midnight
+        let partial = matches!(self, Bucketing::DailyTz(_)) as i64;
+        std::cmp::max(1, (span + window - 1) / window + partial)
+    }
+
+    /// Longest span `window_count` keeps within `max_windows`, at least one window.
+    pub fn max_span(&self, max_windows: i64) -> Duration {
+        match *self {
+            Bucketing::Fixed(window) => window.as_duration() * max_windows.max(1) as i32,
+            Bucketing::DailyTz(_) => Duration::hours(23) * (max_windows - 1).max(1) as i32,
+        }
+    }
+}
+
+/// First instant of `date` in `tz`.
+/// If midnight falls in a DST gap the day starts when the clocks resume.
+fn local_midnight(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+    (0..=2)
+        .find_map(|hours| tz.from_local_datetime(&(midnight + Duration::hours(hours))).earliest())
+        .expect("DST gaps are shorter than 2 hours")
+        .with_timezone(&Utc)
+}
+
+/// Same as `time_windows` but each window ends 1ms before the next local midnight in `tz`.
+fn daily_windows<'a>(start_time: &'a DateTime<Utc>, end_time: &'a DateTime<Utc>, tz: Tz) -> impl Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> + 'a {
+    let next_midnight = move |time: &DateTime<Utc>| {
+        let date = time.with_timezone(&tz).date_naive();
+        local_midnight(tz, date.succ_opt().expect("date in range"))
+    };
+    let window_starts = std::iter::successors(Some(*start_time), move |prev| {
+        let next = next_midnight(prev);
+        if next < *end_time { Some(next) } else { None }
+    });
+    window_starts.map(move |window_start| {
+        let window_end = std::cmp::min(
+            next_midnight(&window_start) - Duration::milliseconds(1),
+            *end_time);
+        (window_start, window_end)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::New_York;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    fn hourly() -> Bucketing {
+        Bucketing::Fixed(WindowSize::hours(1).unwrap())
+    }
+
+    fn window_starts(start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        Bucketing::DailyTz(New_York).windows(&start_time, &end_time)
+            .map(|(window_start, _)| window_start)
+            .collect()
+    }
+
+    #[test]
+    fn test_daily_windows_start_at_local_midnight_across_spring_forward() {
+        // 2025-03-09 New York moves from EST (UTC-5) to EDT (UTC-4)
+        let starts = window_starts(utc(2025, 3, 8, 5), utc(2025, 3, 11, 4));
+
+        assert_eq!(starts, vec![utc(2025, 3, 8, 5), utc(2025, 3, 9, 5), utc(2025, 3, 10, 4)]);
+        assert_eq!(starts[2] - starts[1], Duration::hours(23));
+        assert!(starts.iter().all(|start| start.with_timezone(&New_York).format("%H:%M").to_string() == "00:00"));
+    }
+
+    #[test]
+    fn test_daily_windows_start_at_local_midnight_across_fall_back() {
+        // 2025-11-02 New York moves from EDT (UTC-4) back to EST (UTC-5)
+        let starts = window_starts(utc(2025, 11, 1, 4), utc(2025, 11, 4, 5));
+
+        assert_eq!(starts, vec![utc(2025, 11, 1, 4), utc(2025, 11, 2, 4), utc(2025, 11, 3, 5)]);
+        assert_eq!(starts[2] - starts[1], Duration::hours(25));
+    }
+
+    #[test]
+    fn test_daily_windows_end_before_next_midnight_and_clamp_to_range() {
+        let start_time = utc(2025, 3, 8, 12);
+        let end_time = utc(2025, 3, 9, 12);
+
+        let windows: Vec<_> = Bucketing::DailyTz(New_York).windows(&start_time, &end_time).collect();
+
+        assert_eq!(windows, vec![
+            (start_time, utc(2025, 3, 9, 5) - Duration::milliseconds(1)),
+            (utc(2025, 3, 9, 5), end_time),
+        ]);
+    }
+
+    #[test]
+    fn test_window_count_matches_windows() {
+        let start_time = utc(2025, 3, 8, 12);
+        for end_time in [utc(2025, 3, 8, 13), utc(2025, 3, 8, 15) + Duration::milliseconds(1), utc(2025, 3, 12, 0)] {
+            let fixed = hourly();
+            assert_eq!(fixed.window_count(&start_time, &end_time), fixed.windows(&start_time, &end_time).count() as i64);
+            let daily = Bucketing::DailyTz(New_York);
+            assert!(daily.window_count(&start_time, &end_time) >= daily.windows(&start_time, &end_time).count() as i64);
+        }
+    }
+
+    #[test]
+    fn test_max_span_stays_within_max_windows() {
+        let start_time = utc(2025, 3, 8, 12);
+        for bucketing in [hourly(), Bucketing::DailyTz(New_York)] {
+            let end_time = start_time + bucketing.max_span(10) - Duration::milliseconds(1);
+            assert!(bucketing.window_count(&start_time, &end_time) <= 10, "{:?}", bucketing);
+        }
+    }
+
+    #[test]
+    fn test_fixed_bucketing_matches_time_windows() {
+        let start_time = utc(2025, 3, 8, 0);
+        let end_time = utc(2025, 3, 8, 3);
+
+        let windows: Vec<_> = hourly().windows(&start_time, &end_time).collect();
+
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[1], (utc(2025, 3, 8, 1), utc(2025, 3, 8, 2) - Duration::milliseconds(1)));
+    }
+}
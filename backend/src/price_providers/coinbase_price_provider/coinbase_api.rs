@@ -0,0 +1,85 @@
+pub trait CoinbaseAPI {
+    /// GET /products/{product_id}/candles
+    ///
+    /// Parameters
+    /// granularity INT     YES  Seconds per candle, one of 60, 300, 900, 3600, 21600, 86400.
+    /// start       STRING  NO   ISO 8601 timestamp.
+    /// end         STRING  NO   ISO 8601 timestamp. At most 300 candles per request.
+    ///
+    /// Expected Response (newest first):
+    /// [
+    ///   [
+    ///     1415398768,  // Bucket start time in seconds
+    ///     0.32,        // Low
+    ///     4.2,         // High
+    ///     0.35,        // Open
+    ///     4.2,         // Close
+    ///     12.3         // Volume
+    ///   ]
+    /// ]
+    fn candles(&self,
+        product_id: &str,
+        granularity: i64,
+        start: Option<&str>,
+        end: Option<&str>,
+    ) -> anyhow::Result<String>;
+}
+
+/// `[time, low, high, open, close, volume]`
+pub type Candle = (i64, f64, f64, f64, f64, f64);
+pub type CandlesResponse = Vec<Candle>;
+
+pub struct CoinbaseHttpClient {
+    client: reqwest::blocking::Client,
+    base_url: String,
+}
+
+impl CoinbaseHttpClient {
+    pub fn new() -> Self {
+        Self::with_base_url("https://api.exchange.coinbase.com")
+    }
+
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Default for CoinbaseHttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CoinbaseAPI for CoinbaseHttpClient {
+
+    fn candles(&self,
+        product_id: &str,
+        granularity: i64,
+        start: Option<&str>,
+        end: Option<&str>,
+    ) -> anyhow::Result<String> {
+
+        let mut req = self.client.get(format!("{}/products/{}/candles", self.base_url, product_id))
+            // Coinbase rejects requests without a User-Agent
+            .header(reqwest::header::USER_AGENT, "rust_practice")
+            .query(&[("granularity", granularity.to_string())]);
+
+        for (key, value) in [
+            ("start", &start),
+            ("end", &end),
+        ] {
+            if let Some(v) = value {
+                req = req.query(&[(key, v)]);
+            }
+        }
+
+        let resp = req.send()?.error_for_status()?;
+
+        let text = resp.text()?;
+        Ok(text)
+    }
+
+}
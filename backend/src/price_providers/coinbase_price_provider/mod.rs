@@ -0,0 +1,156 @@
+pub mod coinbase_api;
+
+use super::{PriceError, PricePoint, PriceProvider, PriceSeries};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use coinbase_api::{CandlesResponse, CoinbaseAPI};
+use rust_decimal::Decimal;
+
+/// Quote assets recognised when translating Binance style symbols, longest match wins.
+const QUOTE_ASSETS: [&str; 8] = ["USDC", "USDT", "USD", "EUR", "GBP", "DAI", "BTC", "ETH"];
+
+/// Translates a Binance style symbol (`BTCUSDC`) into a Coinbase product id (`BTC-USDC`).
+/// Symbols already containing a `-` are passed through.
+pub fn coinbase_product_id(symbol: &str) -> anyhow::Result<String> {
+    if symbol.contains('-') {
+        return Ok(symbol.to_string());
+    }
+    QUOTE_ASSETS.iter()
+        .filter(|quote| symbol.len() > quote.len() && symbol.ends_with(*quote))
+        .max_by_key(|quote| quote.len())
+        .map(|quote| format!("{}-{}", &symbol[..symbol.len() - quote.len()], quote))
+        .ok_or_else(|| anyhow::anyhow!("Can't find the quote asset of symbol {}", symbol))
+}
+
+/// Sources prices from Coinbase candles, each one priced at its close.
+pub struct CoinbasePriceProvider {
+    coinbase_api: Box<dyn CoinbaseAPI + Send + Sync>,
+    granularity: i64,
+}
+
+impl CoinbasePriceProvider {
+    /// Coinbase serves at most this many candles per request.
+    const MAX_CANDLES: i64 = 300;
+
+    /// `granularity` is the candle size in seconds, one of 60, 300, 900, 3600, 21600, 86400.
+    pub fn new(coinbase_api: Box<dyn CoinbaseAPI + Send + Sync>, granularity: i64) -> CoinbasePriceProvider {
+        CoinbasePriceProvider { coinbase_api, granularity }
+    }
+
+    pub fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        PriceError::check_range(start_time, end_time)?;
+        let product_id = coinbase_product_id(symbol)?;
+        let page_size = Duration::seconds(self.granularity * Self::MAX_CANDLES);
+
+        let mut prices = Vec::new();
+        let mut page_start = *start_time;
+        while page_start < *end_time {
+            let page_end = std::cmp::min(page_start + page_size, *end_time);
+            let api_response = self.coinbase_api.candles(
+                &product_id,
+                self.granularity,
+                Some( &page_start.to_rfc3339_opts(SecondsFormat::Secs, true) ),
+                Some( &page_end.to_rfc3339_opts(SecondsFormat::Secs, true) ))?;
+            let candles: CandlesResponse = serde_json::from_str(&api_response)?;
+
+            let mut page = Vec::with_capacity(candles.len());
+            for (time, _low, _high, _open, close, _volume) in candles {
+                let Some(timestamp) = DateTime::from_timestamp(time, 0) else { continue };
+                if timestamp < page_start || timestamp >= page_end {
+                    continue;
+                }
+                let price = Decimal::try_from(close).map_err(|_| PriceError::InvalidPrice(close.to_string()))?;
+                page.push(PricePoint { timestamp, price });
+            }
+            // Coinbase returns candles newest first
+            page.sort_by_key(|point| point.timestamp);
+            prices.append(&mut page);
+
+            page_start = page_end;
+        }
+        Ok(prices)
+    }
+}
+
+impl PriceProvider for CoinbasePriceProvider {
+    fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        CoinbasePriceProvider::prices(self, symbol, start_time, end_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::coinbase_api::CoinbaseHttpClient;
+    use chrono::prelude::*;
+    use rust_decimal_macros::dec;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn test_coinbase_product_id_inserts_separator_before_quote() {
+        assert_eq!( coinbase_product_id("BTCUSDC").unwrap(), "BTC-USDC" );
+        assert_eq!( coinbase_product_id("ETHUSD").unwrap(), "ETH-USD" );
+        assert_eq!( coinbase_product_id("ETHBTC").unwrap(), "ETH-BTC" );
+        assert_eq!( coinbase_product_id("BTC-USDC").unwrap(), "BTC-USDC" );
+        assert!( coinbase_product_id("USDC").is_err() );
+    }
+
+    #[test]
+    fn test_coinbase_provider_returns_close_prices_from_two_candles() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,14,2,0).unwrap();
+
+        let _m = mock("GET", "/products/BTC-USDC/candles")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("granularity".into(), "60".into()),
+                Matcher::UrlEncoded("start".into(), "2025-01-27T14:00:00Z".into()),
+                Matcher::UrlEncoded("end".into(), "2025-01-27T14:02:00Z".into()),
+            ]))
+            .with_status(200)
+            .with_body("[[1737986460, 101.0, 107.0, 105.5, 102.25, 8.0], [1737986400, 95.0, 110.0, 100.0, 105.5, 12.5]]")
+            .create();
+
+        let coinbase_api = CoinbaseHttpClient::with_base_url(&mockito::server_url());
+        let provider = CoinbasePriceProvider::new(Box::new(coinbase_api), 60);
+        let prices = provider.prices("BTCUSDC", &start_time, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].timestamp, start_time );
+        assert_eq!( prices[0].price, dec!(105.5) );
+        assert_eq!( prices[1].timestamp, start_time + Duration::minutes(1) );
+        assert_eq!( prices[1].price, dec!(102.25) );
+        _m.assert();
+    }
+
+    #[test]
+    fn test_coinbase_provider_returns_error_on_api_error() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,14,2,0).unwrap();
+
+        let _m = mock("GET", "/products/BTC-USDC/candles")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .create();
+
+        let coinbase_api = CoinbaseHttpClient::with_base_url(&mockito::server_url());
+        let provider = CoinbasePriceProvider::new(Box::new(coinbase_api), 60);
+        assert!( provider.prices("BTCUSDC", &start_time, &end_time).is_err() );
+    }
+
+    #[test]
+    fn test_coinbase_provider_rejects_close_out_of_decimal_range() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,14,1,0).unwrap();
+
+        let _m = mock("GET", "/products/BTC-USDC/candles")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body("[[1737986400, 95.0, 110.0, 100.0, 1e30, 12.5]]")
+            .create();
+
+        let coinbase_api = CoinbaseHttpClient::with_base_url(&mockito::server_url());
+        let provider = CoinbasePriceProvider::new(Box::new(coinbase_api), 60);
+        let err = provider.prices("BTCUSDC", &start_time, &end_time).unwrap_err();
+
+        assert!( matches!(err.downcast_ref::<PriceError>(), Some(PriceError::InvalidPrice(_))), "{}", err );
+    }
+}
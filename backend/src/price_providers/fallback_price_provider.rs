@@ -0,0 +1,102 @@
+use super::{PriceProvider, PriceSeries};
+use chrono::{DateTime, Utc};
+
+/// Tries each provider in order and returns the first successful series,
+/// e.g. Binance first and Coinbase when Binance is down.
+///
+/// An `Ok` with an empty series is a success: the source answered and had no trades,
+/// so the next providers aren't asked. Only errors fall through.
+pub struct FallbackProvider {
+    providers: Vec<Box<dyn PriceProvider + Send + Sync>>,
+}
+
+impl FallbackProvider {
+    pub fn new(providers: Vec<Box<dyn PriceProvider + Send + Sync>>) -> Self {
+        FallbackProvider { providers }
+    }
+}
+
+impl PriceProvider for FallbackProvider {
+    /// Fails only if every provider failed, with all their errors in the message.
+    fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        let mut errors = Vec::new();
+        for provider in &self.providers {
+            match provider.prices(symbol, start_time, end_time) {
+                Ok(prices) => return Ok(prices),
+                Err(err) => errors.push(format!("{:#}", err)),
+            }
+        }
+        anyhow::bail!("All {} price providers failed: {}", self.providers.len(), errors.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_providers::PricePoint;
+    use chrono::prelude::*;
+    use rust_decimal_macros::dec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Answers with a fixed result, counting calls
+    struct StubProvider {
+        result: Result<PriceSeries, String>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl PriceProvider for StubProvider {
+        fn prices(&self, _symbol: &str, _start_time: &DateTime<Utc>, _end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.result.clone().map_err(anyhow::Error::msg)
+        }
+    }
+
+    fn stub(result: Result<PriceSeries, &str>) -> (Box<dyn PriceProvider + Send + Sync>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = StubProvider { result: result.map_err(str::to_string), calls: calls.clone() };
+        (Box::new(provider), calls)
+    }
+
+    fn range() -> (DateTime<Utc>, DateTime<Utc>) {
+        (Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap(), Utc.with_ymd_and_hms(2025,1,27,14,1,0).unwrap())
+    }
+
+    #[test]
+    fn test_fallback_provider_uses_next_provider_on_error() {
+        let (start_time, end_time) = range();
+        let series = vec![PricePoint { timestamp: start_time, price: dec!(105.5) }];
+        let (failing, _) = stub(Err("binance is down"));
+        let (working, _) = stub(Ok(series.clone()));
+
+        let provider = FallbackProvider::new(vec![failing, working]);
+
+        assert_eq!( provider.prices("BTCUSDC", &start_time, &end_time).unwrap(), series );
+    }
+
+    #[test]
+    fn test_fallback_provider_treats_empty_series_as_success() {
+        let (start_time, end_time) = range();
+        let (empty, _) = stub(Ok(Vec::new()));
+        let (next, next_calls) = stub(Ok(vec![PricePoint { timestamp: start_time, price: dec!(1) }]));
+
+        let provider = FallbackProvider::new(vec![empty, next]);
+
+        assert!( provider.prices("BTCUSDC", &start_time, &end_time).unwrap().is_empty() );
+        assert_eq!( next_calls.load(Ordering::Relaxed), 0 );
+    }
+
+    #[test]
+    fn test_fallback_provider_reports_every_error_when_all_fail() {
+        let (start_time, end_time) = range();
+        let (first, _) = stub(Err("binance is down"));
+        let (second, _) = stub(Err("coinbase is down"));
+
+        let provider = FallbackProvider::new(vec![first, second]);
+        let message = provider.prices("BTCUSDC", &start_time, &end_time).unwrap_err().to_string();
+
+        assert!( message.contains("All 2 price providers failed") );
+        assert!( message.contains("binance is down") );
+        assert!( message.contains("coinbase is down") );
+    }
+}
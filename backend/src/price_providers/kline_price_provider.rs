@@ -0,0 +1,265 @@
+use super::binance_price_provider::binance_api::{BinanceAPI, Kline, KlinesResponse, MAX_LIMIT};
+use super::{PriceError, PricePoint, PriceProvider, PriceSeries};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Binance kline intervals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Interval {
+    OneSecond,
+    OneMinute,
+    ThreeMinutes,
+    FiveMinutes,
+    FifteenMinutes,
+    ThirtyMinutes,
+    OneHour,
+    TwoHours,
+    FourHours,
+    SixHours,
+    EightHours,
+    TwelveHours,
+    OneDay,
+    ThreeDays,
+    OneWeek,
+    OneMonth,
+}
+
+impl Interval {
+    /// Value of the `interval` query parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::OneSecond => "1s",
+            Interval::OneMinute => "1m",
+            Interval::ThreeMinutes => "3m",
+            Interval::FiveMinutes => "5m",
+            Interval::FifteenMinutes => "15m",
+            Interval::ThirtyMinutes => "30m",
+            Interval::OneHour => "1h",
+            Interval::TwoHours => "2h",
+            Interval::FourHours => "4h",
+            Interval::SixHours => "6h",
+            Interval::EightHours => "8h",
+            Interval::TwelveHours => "12h",
+            Interval::OneDay => "1d",
+            Interval::ThreeDays => "3d",
+            Interval::OneWeek => "1w",
+            Interval::OneMonth => "1M",
+        }
+    }
+}
+
+/// A kline with parsed numeric fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candle {
+    pub open_time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl TryFrom<Kline> for Candle {
+    type Error = anyhow::Error;
+
+    fn try_from(kline: Kline) -> anyhow::Result<Self> {
+        Ok(Candle {
+            open_time: DateTime::from_timestamp_millis(kline.open_time)
+                .ok_or_else(|| anyhow::anyhow!("Invalid kline open time {}", kline.open_time))?,
+            open: kline.open.parse::<f64>()?,
+            high: kline.high.parse::<f64>()?,
+            low: kline.low.parse::<f64>()?,
+            close: kline.close.parse::<f64>()?,
+            volume: kline.volume.parse::<f64>()?,
+        })
+    }
+}
+
+/// Sources prices from Binance's pre-aggregated klines instead of raw aggTrades.
+///
+/// Each kline becomes a `PricePoint` at its open time priced at its close.
+/// A single request covers up to `KLINES_LIMIT` intervals, so long ranges need
+/// far fewer requests than with `BinancePriceProvider`.
+pub struct KlinePriceProvider {
+    binance_api: Box<dyn BinanceAPI + Send + Sync>,
+    interval: Interval,
+}
+
+impl KlinePriceProvider {
+    pub(crate) const KLINES_LIMIT: i64 = MAX_LIMIT;
+
+    pub fn new(binance_api: Box<dyn BinanceAPI + Send + Sync>, interval: Interval) -> KlinePriceProvider {
+        KlinePriceProvider { binance_api, interval }
+    }
+
+    /// Candles of the given interval covering the range, paging through as many requests as needed.
+    pub fn candles(&self, symbol: &str, interval: Interval, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<Vec<Candle>> {
+        PriceError::check_range(start_time, end_time)?;
+        let mut candles = Vec::new();
+        let mut page_start = start_time.timestamp_millis();
+        loop {
+            let api_response = self.binance_api.klines(
+                symbol,
+                interval.as_str(),
+                Some( page_start ),
+                Some( end_time.timestamp_millis() ),
+                Some( Self::KLINES_LIMIT ))?;
+            let klines: KlinesResponse = serde_json::from_str(&api_response)?;
+            let page_len = klines.len() as i64;
+
+            for kline in klines {
+                page_start = kline.open_time + 1;
+                candles.push(Candle::try_from(kline)?);
+            }
+
+            if page_len < Self::KLINES_LIMIT {
+                break;
+            }
+        }
+        Ok(candles)
+    }
+
+    /// Fetches candles for several intervals at once, e.g. for a multi-timeframe view.
+    ///
+    /// Each interval is fetched independently and keeps its own result, so a failure
+    /// on one interval doesn't discard the others.
+    pub fn multi_interval_klines(&self, symbol: &str, intervals: &[Interval], start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> HashMap<Interval, anyhow::Result<Vec<Candle>>> {
+        intervals.iter()
+            .map(|interval| (*interval, self.candles(symbol, *interval, start_time, end_time)))
+            .collect()
+    }
+
+    pub fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        self.candles(symbol, self.interval, start_time, end_time)?
+            .into_iter()
+            .map(|candle| Ok(PricePoint { timestamp: candle.open_time, price: Decimal::try_from(candle.close)? }))
+            .collect()
+    }
+}
+
+impl PriceProvider for KlinePriceProvider {
+    fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        KlinePriceProvider::prices(self, symbol, start_time, end_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_providers::binance_price_provider::mock_binance_api::MockBinanceAPI;
+    use assert_float_eq::assert_float_absolute_eq;
+    use chrono::prelude::*;
+    use rust_decimal_macros::dec;
+    use mockall::predicate::*;
+
+    const SYMBOL: &str = "BTCUSDC";
+
+    const KLINES_RESPONSE: &str = concat!(
+        r#"[[1737986400000,"100.0","110.0","95.0","105.5","12.5",1737986459999,"1300.0",42,"6.0","630.0","0"],"#,
+        r#"[1737986460000,"105.5","107.0","101.0","102.25","8.0",1737986519999,"830.0",17,"4.0","410.0","0"]]"#
+    );
+
+    #[test]
+    fn test_kline_provider_maps_close_prices_to_price_series() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,14,2,0).unwrap();
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines()
+            .times(1)
+            .with(
+                eq(SYMBOL),
+                eq("1m"),
+                eq(Some(start_time.timestamp_millis())),
+                eq(Some(end_time.timestamp_millis())),
+                always())
+            .returning(|_,_,_,_,_| Ok(KLINES_RESPONSE.to_string()));
+
+        let provider = KlinePriceProvider::new(Box::new(mock_api), Interval::OneMinute);
+        let prices = provider.prices(SYMBOL, &start_time, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].timestamp, start_time );
+        assert_eq!( prices[0].price, dec!(105.5) );
+        assert_eq!( prices[1].timestamp, start_time + chrono::Duration::minutes(1) );
+        assert_eq!( prices[1].price, dec!(102.25) );
+    }
+
+    #[test]
+    fn test_kline_provider_rejects_reversed_or_empty_range() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,14,1,0).unwrap();
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines().never();
+
+        let provider = KlinePriceProvider::new(Box::new(mock_api), Interval::OneMinute);
+        assert!( provider.candles(SYMBOL, Interval::OneMinute, &end_time, &start_time).is_err() );
+        assert!( provider.candles(SYMBOL, Interval::OneMinute, &start_time, &start_time).is_err() );
+    }
+
+    #[test]
+    fn test_kline_provider_returns_error_on_invalid_close_price() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,14,1,0).unwrap();
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines()
+            .returning(|_,_,_,_,_| Ok(
+                r#"[[1737986400000,"100.0","110.0","95.0","notafloat","12.5",1737986459999,"1300.0",42,"6.0","630.0","0"]]"#.to_string()));
+
+        let provider = KlinePriceProvider::new(Box::new(mock_api), Interval::OneMinute);
+        assert!( provider.prices(SYMBOL, &start_time, &end_time).is_err() );
+    }
+
+    #[test]
+    fn test_multi_interval_klines_returns_candles_keyed_by_interval() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,15,0,0).unwrap();
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines()
+            .times(1)
+            .with(eq(SYMBOL), eq("1m"), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(KLINES_RESPONSE.to_string()));
+        mock_api.expect_klines()
+            .times(1)
+            .with(eq(SYMBOL), eq("1h"), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(
+                r#"[[1737986400000,"100.0","120.0","90.0","110.0","300.0",1737989999999,"33000.0",900,"150.0","16500.0","0"]]"#.to_string()));
+
+        let provider = KlinePriceProvider::new(Box::new(mock_api), Interval::OneMinute);
+        let klines = provider.multi_interval_klines(SYMBOL, &[Interval::OneMinute, Interval::OneHour], &start_time, &end_time);
+
+        assert_eq!( klines.len(), 2 );
+        let minute_candles = klines[&Interval::OneMinute].as_ref().unwrap();
+        assert_eq!( minute_candles.len(), 2 );
+        assert_float_absolute_eq!( minute_candles[1].close, 102.25 );
+        let hour_candles = klines[&Interval::OneHour].as_ref().unwrap();
+        assert_eq!( hour_candles.len(), 1 );
+        assert_eq!( hour_candles[0].open_time, start_time );
+        assert_float_absolute_eq!( hour_candles[0].high, 120.0 );
+        assert_float_absolute_eq!( hour_candles[0].volume, 300.0 );
+    }
+
+    #[test]
+    fn test_multi_interval_klines_isolates_failing_interval() {
+        let start_time = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025,1,27,15,0,0).unwrap();
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines()
+            .with(eq(SYMBOL), eq("1m"), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(KLINES_RESPONSE.to_string()));
+        mock_api.expect_klines()
+            .with(eq(SYMBOL), eq("5m"), always(), always(), always())
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
+
+        let provider = KlinePriceProvider::new(Box::new(mock_api), Interval::OneMinute);
+        let klines = provider.multi_interval_klines(SYMBOL, &[Interval::OneMinute, Interval::FiveMinutes], &start_time, &end_time);
+
+        assert_eq!( klines[&Interval::OneMinute].as_ref().unwrap().len(), 2 );
+        assert!( klines[&Interval::FiveMinutes].is_err() );
+    }
+}
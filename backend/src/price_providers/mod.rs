@@ -1,294 +1,3023 @@
-mod binance_price_provider;
+pub mod binance_price_provider;
+pub mod bucketing;
+pub mod coinbase_price_provider;
+pub mod fallback_price_provider;
+#[cfg(feature = "async")]
+pub mod async_price_provider;
+pub mod kline_price_provider;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+mod price_error;
+pub mod series;
+mod symbol;
+mod time_range;
 
-use binance_price_provider::binance_api::{BinanceAPI, AggTradesResponse};
+pub use bucketing::Bucketing;
+pub use price_error::PriceError;
+pub use symbol::Symbol;
+pub use time_range::TimeRange;
+
+use anyhow::Context;
+use crate::retry::{with_backoff, BackoffStrategy, RetryPolicy};
+use binance_price_provider::binance_api::{BinanceAPI, AggTrade, AggTrades, AvgPrice, BinanceErrorResponse, ExchangeInfoResponse, KlinesResponse, OrderBook, StrictAggTradesResponse, SymbolInfo, Ticker24hr};
+use kline_price_provider::{Interval, KlinePriceProvider};
 use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use series::Aggregation;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+/// Serializes as `{"timestamp": "<RFC 3339>", "price": <number>}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PricePoint {
     pub timestamp: DateTime<Utc>,
-    pub price: f64,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
 }
 pub type PriceSeries = Vec<PricePoint>;
+/// Start of a window `BinancePriceProvider::prices_partial` couldn't fetch, and why.
+pub type WindowFailure = (DateTime<Utc>, PriceError);
+/// `(window_start, window_end)`, both inclusive.
+pub type TimeWindow = (DateTime<Utc>, DateTime<Utc>);
+
+/// A `PricePoint` with its timestamp in a given timezone, see `BinancePriceProvider::prices_in_tz`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalPricePoint {
+    pub timestamp: DateTime<Tz>,
+    pub price: Decimal,
+}
+
+impl PricePoint {
+    /// The same point at the wall-clock time of `tz`, its offset follows DST.
+    pub fn in_tz(&self, tz: Tz) -> LocalPricePoint {
+        LocalPricePoint { timestamp: self.timestamp.with_timezone(&tz), price: self.price }
+    }
+}
+
+/// A source of historical prices for a symbol over a time range.
+pub trait PriceProvider {
+    fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries>;
+}
+
+/// Where an incremental backfill of each symbol left off, e.g. `LocalDb`.
+pub trait FetchProgress {
+    fn get_last_fetched(&self, symbol: &str) -> anyhow::Result<Option<DateTime<Utc>>>;
+    fn set_last_fetched(&self, symbol: &str, timestamp: &DateTime<Utc>) -> anyhow::Result<()>;
+}
+
+/// The tokens of interest, e.g. `LocalDb`.
+///
+/// Tokens are base assets like `BTC`, the quote asset is the provider's, see `symbol_for_base`.
+pub trait Watchlist {
+    /// Base assets to watch, seeded with `defaults` while there are none.
+    fn tokens_or_defaults(&self, defaults: &[&str]) -> anyhow::Result<Vec<String>>;
+}
+
+/// How response bodies are checked against the documented Binance schema.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SchemaMode {
+    /// Unknown fields are ignored.
+    #[default]
+    Lenient,
+    /// Unknown fields are a decoding error, useful to alert on schema drift.
+    Strict,
+}
+
+fn decode_with_schema(api_response: &str, schema_mode: SchemaMode) -> serde_json::Result<AggTrades> {
+    if schema_mode == SchemaMode::Strict {
+        serde_json::from_str::<StrictAggTradesResponse>(api_response)?;
+    }
+    serde_json::from_str::<AggTrades>(api_response)
+}
+
+/// Decodes a raw aggTrades response. A Binance error body is reported with its code
+/// and message rather than as a failure to decode trades.
+fn decode_agg_trades(symbol: &str, api_response: &str, schema_mode: SchemaMode) -> Result<AggTrades, PriceError> {
+    decode_with_schema(api_response, schema_mode).map_err(|err| {
+        match serde_json::from_str::<BinanceErrorResponse>(api_response) {
+            Ok(error_response) => PriceError::Binance { symbol: symbol.to_string(), code: error_response.code, msg: error_response.msg },
+            Err(_) => PriceError::Decode(err),
+        }
+    })
+}
+
+/// Storage for already fetched prices, e.g. `LocalDb`.
+pub trait PriceCache {
+    /// Cached prices of `symbol` with timestamps in `[start_time, end_time]`, ascending.
+    fn read_cached_prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries>;
+    fn cache_prices(&self, symbol: &str, series: &PriceSeries) -> anyhow::Result<()>;
+}
+
+/// Write-through storage keeping every index of fetched prices in sync, e.g. `LocalDb`.
+pub trait PriceStore {
+    /// Stores `series` in all indexes at once, either everything is written or nothing.
+    fn store_prices(&self, symbol: &str, series: &PriceSeries) -> anyhow::Result<()>;
+}
+
+/// Runs `fetch` for up to `concurrency` symbols at once, keyed by symbol.
+fn for_each_parallel<T: Send>(symbols: &[&str], concurrency: usize, fetch: impl Fn(&str) -> T + Sync) -> HashMap<String, T> {
+    let next_symbol = AtomicUsize::new(0);
+    let results = Mutex::new(HashMap::with_capacity(symbols.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, symbols.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(symbol) = symbols.get(next_symbol.fetch_add(1, Ordering::Relaxed)) {
+                    let result = fetch(symbol);
+                    results.lock().unwrap().insert(symbol.to_string(), result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// Prices of the trades in a raw aggTrades response, exactly as Binance wrote them.
+fn trade_prices(symbol: &str, api_response: &str, schema_mode: SchemaMode) -> Result<Vec<Decimal>, PriceError> {
+    let trades = decode_agg_trades(symbol, api_response, schema_mode)?;
+    Ok(trades.iter().map(|trade| trade.price).collect())
+}
+
+/// Price representing the trades of a raw aggTrades response, `None` when there were no trades.
+///
+/// `First` and `Last` pick the trade with the smallest and largest `T`, trades with the
+/// same time are ordered by aggregate trade id.
+fn window_price(symbol: &str, api_response: &str, schema_mode: SchemaMode, aggregation: Aggregation) -> Result<Option<Decimal>, PriceError> {
+    let trades: Vec<_> = decode_agg_trades(symbol, api_response, schema_mode)?
+        .iter()
+        .map(|trade| ((trade.time, trade.id), trade.price))
+        .collect();
+    let price = match aggregation {
+        Aggregation::Mean => mean(&trades.iter().map(|(_, price)| *price).collect::<Vec<_>>()),
+        Aggregation::First => trades.iter().min_by_key(|(order, _)| *order).map(|(_, price)| *price),
+        Aggregation::Last => trades.iter().max_by_key(|(order, _)| *order).map(|(_, price)| *price),
+        Aggregation::Min => trades.iter().map(|(_, price)| *price).min(),
+        Aggregation::Max => trades.iter().map(|(_, price)| *price).max(),
+    };
+    if price.is_none() {
+        tracing::warn!("No trades in window");
+    } else {
+        tracing::debug!(trade_count = trades.len(), "Fetched window");
+    }
+    Ok(price)
+}
+
+/// `(price, quantity)` of the trades in a raw aggTrades response.
+fn trades(symbol: &str, api_response: &str, schema_mode: SchemaMode) -> Result<Vec<(f64, f64)>, PriceError> {
+    let trades = decode_agg_trades(symbol, api_response, schema_mode)?;
+    trades.iter()
+        .map(|trade| Ok((to_f64(trade.price)?, to_f64(trade.quantity)?)))
+        .collect()
+}
+
+/// `value` as the nearest `f64`, fails rather than making up a NaN if it has none.
+pub(crate) fn to_f64(value: Decimal) -> Result<f64, PriceError> {
+    value.to_f64().ok_or_else(|| PriceError::InvalidPrice(value.to_string()))
+}
+
+fn mean(prices: &[Decimal]) -> Option<Decimal> {
+    if prices.is_empty() {
+        return None;
+    }
+    Some(prices.iter().sum::<Decimal>() / Decimal::from(prices.len()))
+}
+
+/// Sums of `price * quantity` and of `quantity` over `trades`.
+fn notional_and_quantity(trades: &[AggTrade]) -> (Decimal, Decimal) {
+    trades.iter().fold((Decimal::ZERO, Decimal::ZERO), |(notional, quantity), trade| {
+        (notional + trade.price * trade.quantity, quantity + trade.quantity)
+    })
+}
+
+/// Yields the `(window_start, window_end)` bounds covering `[start_time, end_time]`.
+/// Each window ends 1ms before the next one starts, the last one is clamped to `end_time`.
+fn time_windows<'a>(start_time: &'a DateTime<Utc>, end_time: &'a DateTime<Utc>, window: Duration) -> impl Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> + 'a {
+    let window_starts = std::iter::successors(Some(*start_time), move |prev| {
+        let next = *prev + window;
+        if next < *end_time { Some(next) } else { None }
+    });
+    window_starts.map(move |window_start| {
+        let window_end = std::cmp::min(
+            window_start + window - Duration::milliseconds(1),
+            *end_time);
+        (window_start, window_end)
+    })
+}
+
+/// What to do when exchangeInfo can't be fetched for a feature that depends on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExchangeInfoFallback {
+    /// Fail the call, the exchangeInfo error is returned.
+    #[default]
+    Error,
+    /// Keep going with permissive defaults: symbols are only checked to look like
+    /// a Binance symbol and precision is `DEFAULT_PRICE_PRECISION`.
+    Permissive,
+}
+
+/// Decimals assumed for prices when the exchange filters are unknown.
+pub const DEFAULT_PRICE_PRECISION: u32 = 8;
+
+/// Loose format check used when exchangeInfo isn't available.
+fn looks_like_symbol(symbol: &str) -> bool {
+    (5..=20).contains(&symbol.len())
+        && symbol.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Number of decimals in a tick size like `"0.01000000"`.
+fn tick_size_precision(tick_size: &str) -> u32 {
+    tick_size.split_once('.')
+        .map(|(_, decimals)| decimals.trim_end_matches('0').len() as u32)
+        .unwrap_or(0)
+}
+
+/// Size of the windows prices are averaged over, always positive.
+///
+/// Built through unit named constructors so call sites state their intent,
+/// e.g. `WindowSize::minutes(5)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowSize(Duration);
+
+impl WindowSize {
+    fn positive(duration: Duration) -> anyhow::Result<WindowSize> {
+        anyhow::ensure!(duration > Duration::zero(), "Window size must be positive, got {}", duration);
+        Ok(WindowSize(duration))
+    }
+
+    pub fn seconds(n: i64) -> anyhow::Result<WindowSize> {
+        Self::positive(Duration::try_seconds(n).context("Window size out of range")?)
+    }
+
+    pub fn minutes(n: i64) -> anyhow::Result<WindowSize> {
+        Self::positive(Duration::try_minutes(n).context("Window size out of range")?)
+    }
+
+    pub fn hours(n: i64) -> anyhow::Result<WindowSize> {
+        Self::positive(Duration::try_hours(n).context("Window size out of range")?)
+    }
+
+    pub fn milliseconds(n: i64) -> anyhow::Result<WindowSize> {
+        Self::positive(Duration::try_milliseconds(n).context("Window size out of range")?)
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<WindowSize> for Duration {
+    fn from(window_size: WindowSize) -> Self {
+        window_size.0
+    }
+}
+
+/// How windows without trades are represented in the output series.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FillPolicy {
+    /// Empty windows are left out, as `prices` does.
+    #[default]
+    Skip,
+    /// Empty windows repeat the last known price.
+    ForwardFill,
+    /// Empty windows are interpolated between the surrounding known prices.
+    /// Trailing empty windows have nothing to interpolate towards and are left out.
+    Linear,
+}
+
+/// Order of the points in a returned series.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Order {
+    /// Oldest first, as `prices` does.
+    #[default]
+    Ascending,
+    /// Newest first.
+    Descending,
+}
+
+/// Turns per-window prices into a series, filling empty windows according to `policy`.
+/// Empty windows before the first known price are always left out.
+fn fill_gaps(window_prices: Vec<(DateTime<Utc>, Option<Decimal>)>, policy: FillPolicy) -> PriceSeries {
+    let known: Vec<(usize, Decimal)> = window_prices.iter().enumerate()
+        .filter_map(|(i, (_, price))| price.map(|price| (i, price)))
+        .collect();
+
+    let mut prices = Vec::with_capacity(window_prices.len());
+    let mut next_known: usize = 0;
+    for (i, (timestamp, price)) in window_prices.into_iter().enumerate() {
+        if let Some(price) = price {
+            next_known += 1;
+            prices.push(PricePoint { timestamp, price });
+            continue;
+        }
+        // known[next_known - 1] precedes this window, known[next_known] follows it
+        let Some(&(prev_i, prev_price)) = next_known.checked_sub(1).and_then(|k| known.get(k)) else {
+            continue;
+        };
+        let filled = match (policy, known.get(next_known)) {
+            (FillPolicy::Skip, _) => None,
+            (FillPolicy::ForwardFill, _) => Some(prev_price),
+            (FillPolicy::Linear, Some(&(next_i, next_price))) => {
+                // Dividing last keeps evenly spaced prices exact
+                Some(prev_price + (next_price - prev_price) * Decimal::from(i - prev_i) / Decimal::from(next_i - prev_i))
+            }
+            (FillPolicy::Linear, None) => None,
+        };
+        if let Some(price) = filled {
+            prices.push(PricePoint { timestamp, price });
+        }
+    }
+    prices
+}
+
+/// Mean of the trailing `window` prices at each point from index `window - 1` onward,
+/// stamped with the timestamp of the window's last point.
+///
+/// The result has `series.len() - window + 1` points, so it's empty when `window` is 0
+/// or longer than the series.
+pub fn moving_average(series: &PriceSeries, window: usize) -> PriceSeries {
+    if window == 0 {
+        return Vec::new();
+    }
+    series.windows(window)
+        .map(|points| PricePoint {
+            timestamp: points[window - 1].timestamp,
+            price: points.iter().map(|point| point.price).sum::<Decimal>() / Decimal::from(window),
+        })
+        .collect()
+}
+
+/// Price summary of the trades in a window.
+#[derive(Clone, Debug)]
+pub struct PricePointStats {
+    pub timestamp: DateTime<Utc>,
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Number of trades in the window.
+    pub count: usize,
+    /// Mean quantity per trade, `sum(q) / count`.
+    pub avg_trade_size: f64,
+}
+
+impl PricePointStats {
+    /// From the `(price, quantity)` of the window trades, `None` when there are none.
+    fn from_trades(timestamp: DateTime<Utc>, trades: &[(f64, f64)]) -> Option<Self> {
+        if trades.is_empty() {
+            return None;
+        }
+        let count = trades.len();
+        let prices = trades.iter().map(|(price, _)| *price);
+        Some(PricePointStats {
+            timestamp,
+            avg: prices.clone().sum::<f64>() / count as f64,
+            min: prices.clone().fold(f64::INFINITY, f64::min),
+            max: prices.fold(f64::NEG_INFINITY, f64::max),
+            count,
+            avg_trade_size: trades.iter().map(|(_, quantity)| quantity).sum::<f64>() / count as f64,
+        })
+    }
+}
+
+/// Latest price against its trailing average.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceComparison {
+    pub latest: f64,
+    pub average: f64,
+    /// `(latest - average) / average` as a percentage, negative when below average.
+    pub pct_diff: f64,
+}
+
+/// Retries left for a whole call, shared by all of its windows.
+struct RetryBudget(AtomicU32);
+
+impl RetryBudget {
+    fn new(max_retries: u32) -> Self {
+        RetryBudget(AtomicU32::new(max_retries))
+    }
+
+    /// Takes one retry from the budget, `false` once it's exhausted.
+    fn try_spend(&self) -> bool {
+        self.0.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| left.checked_sub(1)).is_ok()
+    }
+}
 
 pub struct BinancePriceProvider {
-    binance_api: Box<dyn BinanceAPI>,
+    binance_api: Box<dyn BinanceAPI + Send + Sync>,
+    exchange_info_fallback: ExchangeInfoFallback,
+    schema_mode: SchemaMode,
+    aggregation: Aggregation,
+    bucketing: Bucketing,
+    retry_budget: u32,
+    retry_backoff: Arc<dyn BackoffStrategy + Send + Sync>,
+    max_windows: i64,
+    validate_symbols: bool,
+    allowed_symbols: Option<HashSet<String>>,
+    blocked_symbols: HashSet<String>,
+    quote_asset: String,
+    exchange_info_cache: Mutex<Option<Arc<ExchangeInfoResponse>>>,
 }
 
-impl BinancePriceProvider {
-    const TIME_WINDOW: Duration = Duration::minutes(1);
+impl BinancePriceProvider {
+    /// Default window size.
+    const TIME_WINDOW: Duration = Duration::minutes(1);
+
+    /// How far back `prices_incremental` starts for a symbol fetched for the first time.
+    pub const DEFAULT_INCREMENTAL_LOOKBACK: Duration = Duration::hours(1);
+
+    /// Clock offsets beyond this are worth a warning, requested trades may be missed.
+    pub const CLOCK_SKEW_WARNING: Duration = Duration::seconds(1);
+
+    /// Most windows a single call may request unless changed with `with_max_windows`.
+    pub const DEFAULT_MAX_WINDOWS: i64 = 10_000;
+
+    /// Order book levels `mid_price` requests, the smallest (and cheapest) depth Binance offers.
+    const MID_PRICE_DEPTH: i64 = 5;
+
+    /// Quote `prices_for_base` pairs base assets with unless changed with `with_quote_asset`.
+    pub const DEFAULT_QUOTE_ASSET: &'static str = "USDT";
+
+    /// Most trades Binance returns per aggTrades request, used by `prices_by_id`.
+    pub const AGG_TRADES_PAGE_LIMIT: i64 = binance_price_provider::binance_api::MAX_LIMIT;
+
+    pub fn new(binance_api: Box<dyn BinanceAPI + Send + Sync>) -> BinancePriceProvider {
+        BinancePriceProvider {
+            binance_api,
+            exchange_info_fallback: ExchangeInfoFallback::default(),
+            schema_mode: SchemaMode::default(),
+            aggregation: Aggregation::default(),
+            bucketing: Bucketing::Fixed(WindowSize(Self::TIME_WINDOW)),
+            retry_budget: 0,
+            retry_backoff: RetryPolicy::default().backoff,
+            max_windows: Self::DEFAULT_MAX_WINDOWS,
+            validate_symbols: false,
+            allowed_symbols: None,
+            blocked_symbols: HashSet::new(),
+            quote_asset: Self::DEFAULT_QUOTE_ASSET.to_string(),
+            exchange_info_cache: Mutex::new(None),
+        }
+    }
+
+    /// Averages prices over windows of the given size instead of the default 1 minute.
+    pub fn with_window_size(mut self, window_size: WindowSize) -> Self {
+        self.bucketing = Bucketing::Fixed(window_size);
+        self
+    }
+
+    /// Retries failed window requests, up to `max_retries` in total across all windows
+    /// of a single `prices` call, after which the call fails with the last error.
+    /// This bounds how long a call can take during a partial outage; retries are off by default.
+    /// Only request failures are retried, not malformed responses or unknown symbols.
+    pub fn with_retry_budget(mut self, max_retries: u32) -> Self {
+        self.retry_budget = max_retries;
+        self
+    }
+
+    /// How long to wait before retrying a window from the retry budget, the
+    /// `RetryPolicy` default unless changed.
+    pub fn with_retry_backoff(mut self, backoff: Arc<dyn BackoffStrategy + Send + Sync>) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Rejects ranges needing more than `max_windows` windows before any request is made,
+    /// so a mistyped range can't fire off hundreds of thousands of calls.
+    pub fn with_max_windows(mut self, max_windows: i64) -> Self {
+        self.max_windows = max_windows;
+        self
+    }
+
+    /// Splits ranges into windows as given, e.g. calendar days with `Bucketing::DailyTz`.
+    pub fn with_bucketing(mut self, bucketing: Bucketing) -> Self {
+        self.bucketing = bucketing;
+        self
+    }
+
+    pub fn with_schema_mode(mut self, schema_mode: SchemaMode) -> Self {
+        self.schema_mode = schema_mode;
+        self
+    }
+
+    /// How the trades of a window become its price, the mean by default.
+    /// Use `Aggregation::Last` for the window close.
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    pub fn with_exchange_info_fallback(mut self, exchange_info_fallback: ExchangeInfoFallback) -> Self {
+        self.exchange_info_fallback = exchange_info_fallback;
+        self
+    }
+
+    /// Makes `prices` check the symbol against exchangeInfo first and fail with
+    /// `PriceError::UnknownSymbol` instead of fetching windows for a symbol that isn't listed.
+    pub fn with_validate_symbols(mut self, validate_symbols: bool) -> Self {
+        self.validate_symbols = validate_symbols;
+        self
+    }
+
+    /// Only `symbols` can be fetched, others fail with `PriceError::SymbolNotAllowed`
+    /// before any request is made.
+    pub fn with_allowed_symbols(mut self, symbols: &[&str]) -> Self {
+        self.allowed_symbols = Some(symbols.iter().map(|symbol| symbol.to_string()).collect());
+        self
+    }
+
+    /// `symbols` can't be fetched, they fail with `PriceError::SymbolNotAllowed`
+    /// before any request is made. Takes precedence over the allowed symbols.
+    pub fn with_blocked_symbols(mut self, symbols: &[&str]) -> Self {
+        self.blocked_symbols = symbols.iter().map(|symbol| symbol.to_string()).collect();
+        self
+    }
+
+    /// Quote asset `prices_for_base` appends to base assets, e.g. `USDC`.
+    pub fn with_quote_asset(mut self, quote_asset: &str) -> Self {
+        self.quote_asset = quote_asset.trim().to_uppercase();
+        self
+    }
+
+    /// Symbol trading `base` against the quote asset, e.g. `BTCUSDT` for ` btc`.
+    pub fn symbol_for_base(&self, base: &str) -> String {
+        format!("{}{}", base.trim().to_uppercase(), self.quote_asset)
+    }
+
+    fn check_allowed(&self, symbol: &str) -> Result<(), PriceError> {
+        let allowed = !self.blocked_symbols.contains(symbol)
+            && self.allowed_symbols.as_ref().is_none_or(|allowed| allowed.contains(symbol));
+        if allowed { Ok(()) } else { Err(PriceError::SymbolNotAllowed(symbol.to_string())) }
+    }
+
+    /// Fetches exchangeInfo, `None` when unavailable and the fallback is permissive.
+    /// The listing rarely changes, so it's fetched once and kept for the provider's lifetime.
+    fn exchange_info(&self) -> anyhow::Result<Option<Arc<ExchangeInfoResponse>>> {
+        let mut cache = self.exchange_info_cache.lock().unwrap();
+        if let Some(exchange_info) = cache.as_ref() {
+            return Ok(Some(exchange_info.clone()));
+        }
+        let result = self.binance_api.exchange_info()
+            .and_then(|response| Ok(serde_json::from_str::<ExchangeInfoResponse>(&response)?));
+        match (result, self.exchange_info_fallback) {
+            (Ok(exchange_info), _) => Ok(Some(cache.insert(Arc::new(exchange_info)).clone())),
+            (Err(_), ExchangeInfoFallback::Permissive) => Ok(None),
+            (Err(err), ExchangeInfoFallback::Error) => Err(err.context("exchangeInfo is unavailable")),
+        }
+    }
+
+    fn symbol_info<'a>(exchange_info: &'a ExchangeInfoResponse, symbol: &str) -> Option<&'a SymbolInfo> {
+        exchange_info.symbols.iter().find(|info| info.symbol == symbol)
+    }
+
+    /// Whether `symbol` is listed on the exchange.
+    pub fn is_valid_symbol(&self, symbol: &str) -> anyhow::Result<bool> {
+        match self.exchange_info()? {
+            Some(exchange_info) => Ok(Self::symbol_info(&exchange_info, symbol).is_some()),
+            None => Ok(looks_like_symbol(symbol)),
+        }
+    }
+
+    /// Number of decimals prices of `symbol` are quoted with, from its tick size.
+    pub fn price_precision(&self, symbol: &str) -> anyhow::Result<u32> {
+        let Some(exchange_info) = self.exchange_info()? else {
+            return Ok(DEFAULT_PRICE_PRECISION);
+        };
+        let symbol_info = Self::symbol_info(&exchange_info, symbol)
+            .with_context(|| format!("Symbol {} is not listed", symbol))?;
+        Ok(symbol_info.filters.iter()
+            .find(|filter| filter.filter_type == "PRICE_FILTER")
+            .and_then(|filter| filter.tick_size.as_deref())
+            .map(tick_size_precision)
+            .unwrap_or(DEFAULT_PRICE_PRECISION))
+    }
+
+    fn retry_budget(&self) -> RetryBudget {
+        RetryBudget::new(self.retry_budget)
+    }
+
+    fn fetch_agg_trades_for_window(&self, symbol: &str, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>, retry_budget: &RetryBudget) -> Result<String, PriceError> {
+        self.fetch_agg_trades(symbol, None, Some(window_start.timestamp_millis()), Some(window_end.timestamp_millis()), None, retry_budget)
+    }
+
+    /// Every trade of the window. The time query returns at most `AGG_TRADES_PAGE_LIMIT`
+    /// trades, a full page is continued by id until a trade falls past `window_end`.
+    fn fetch_all_agg_trades_for_window(&self, symbol: &str, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>, retry_budget: &RetryBudget) -> Result<AggTrades, PriceError> {
+        let limit = Some(Self::AGG_TRADES_PAGE_LIMIT);
+        let api_response = self.fetch_agg_trades(symbol, None, Some(window_start.timestamp_millis()), Some(window_end.timestamp_millis()), limit, retry_budget)?;
+        let mut trades = decode_agg_trades(symbol, &api_response, self.schema_mode)?;
+        let mut page_len = trades.len();
+        while page_len as i64 == Self::AGG_TRADES_PAGE_LIMIT {
+            let Some(last) = trades.last() else { break };
+            let api_response = self.fetch_agg_trades(symbol, Some(last.id + 1), None, None, limit, retry_budget)?;
+            let page = decode_agg_trades(symbol, &api_response, self.schema_mode)?;
+            page_len = page.len();
+            let past_window = page.iter().any(|trade| trade.time > window_end.timestamp_millis());
+            trades.extend(page.into_iter().take_while(|trade| trade.time <= window_end.timestamp_millis()));
+            if past_window {
+                break;
+            }
+        }
+        Ok(trades)
+    }
+
+    fn fetch_agg_trades(&self, symbol: &str, from_id: Option<i64>, start_time: Option<i64>, end_time: Option<i64>, limit: Option<i64>, retry_budget: &RetryBudget) -> Result<String, PriceError> {
+        self.check_allowed(symbol)?;
+        let policy = RetryPolicy { max_retries: self.retry_budget, backoff: self.retry_backoff.clone() };
+        let fetch = || self.binance_api.agg_trades(symbol, from_id, start_time, end_time, limit)
+            .map_err(|err| PriceError::from_api_error(symbol, err));
+        with_backoff(&policy, fetch, |err| {
+            let retry = matches!(err, PriceError::Http(_) | PriceError::RateLimited) && retry_budget.try_spend();
+            if retry {
+                tracing::debug!(error = %err, "Retrying window from the retry budget");
+            }
+            retry
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, retry_budget), fields(%window_start, %window_end))]
+    fn fetch_avg_price_for_window(&self, symbol: &str, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>, retry_budget: &RetryBudget) -> Result<Option<Decimal>, PriceError> {
+        let api_response = self.fetch_agg_trades_for_window(symbol, window_start, window_end, retry_budget)?;
+        window_price(symbol, &api_response, self.schema_mode, self.aggregation)
+    }
+
+    /// Fails unless the range is ordered and needs at most `max_windows` windows.
+    fn check_range(&self, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<(), PriceError> {
+        PriceError::check_range(start_time, end_time)?;
+        let count = self.bucketing.window_count(start_time, end_time);
+        if count > self.max_windows {
+            return Err(PriceError::TooManyWindows { count, max: self.max_windows });
+        }
+        Ok(())
+    }
+
+    /// Checks every fetch over a range makes before its first request, returns the
+    /// normalized symbol to send.
+    fn check_request(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<Symbol, PriceError> {
+        self.check_range(start_time, end_time)?;
+        self.check_symbol(symbol)
+    }
+
+    /// Normalizes `symbol` as `Symbol` does, then fails unless it's allowed and,
+    /// with `validate_symbols`, listed.
+    fn check_symbol(&self, symbol: &str) -> Result<Symbol, PriceError> {
+        let symbol = Symbol::new(symbol)?;
+        self.check_allowed(symbol.as_str())?;
+        if self.validate_symbols && !self.is_valid_symbol(symbol.as_str()).map_err(|err| PriceError::from_api_error(symbol.as_str(), err))? {
+            return Err(PriceError::UnknownSymbol(symbol.to_string()));
+        }
+        Ok(symbol)
+    }
+
+    fn windows<'a>(&self, start_time: &'a DateTime<Utc>, end_time: &'a DateTime<Utc>) -> impl Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> + 'a {
+        self.bucketing.windows(start_time, end_time)
+    }
+
+    /// Average price of each window in the range, windows without trades are left out.
+    ///
+    /// `symbol` is a `&str` or `&Symbol`, strings are validated and normalized first.
+    pub fn prices<S>(&self, symbol: S, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<PriceSeries, PriceError>
+    where
+        S: TryInto<Symbol, Error = PriceError>,
+    {
+        self.prices_ordered(symbol, start_time, end_time, Order::Ascending)
+    }
+
+    /// `prices` over `range`, e.g. one built with `TimeRange::aligned_to` so windows
+    /// start at fixed boundaries.
+    pub fn prices_in_range<S>(&self, symbol: S, range: &TimeRange) -> Result<PriceSeries, PriceError>
+    where
+        S: TryInto<Symbol, Error = PriceError>,
+    {
+        self.prices(symbol, &range.start, &range.end)
+    }
+
+    /// Same as `prices` but going on past failed windows, so a backfill makes progress and
+    /// only the gaps need retrying. The start of each failed window is returned with its error.
+    ///
+    /// Fails as a whole only for what `prices` checks before its first request.
+    pub fn prices_partial<S>(&self, symbol: S, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<(PriceSeries, Vec<WindowFailure>), PriceError>
+    where
+        S: TryInto<Symbol, Error = PriceError>,
+    {
+        let symbol = symbol.try_into()?;
+        let symbol = symbol.as_str();
+        self.check_request(symbol, start_time, end_time)?;
+        let retry_budget = self.retry_budget();
+        let mut prices = Vec::new();
+        let mut failures = Vec::new();
+        for (window_start, window_end) in self.windows(start_time, end_time) {
+            match self.fetch_avg_price_for_window(symbol, &window_start, &window_end, &retry_budget) {
+                Ok(Some(price)) => prices.push(PricePoint { timestamp: window_start, price }),
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::warn!(%window_start, error = %err, "Skipping failed window");
+                    failures.push((window_start, err));
+                }
+            }
+        }
+        Ok((prices, failures))
+    }
+
+    /// The `(window_start, window_end)` bounds `prices` would request, without fetching
+    /// any trades, e.g. to estimate the request weight of a backfill up front.
+    ///
+    /// Fails where `prices` would before its first request, so with `validate_symbols`
+    /// exchangeInfo may be fetched to check the symbol.
+    pub fn plan<S>(&self, symbol: S, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<Vec<TimeWindow>, PriceError>
+    where
+        S: TryInto<Symbol, Error = PriceError>,
+    {
+        let symbol = symbol.try_into()?;
+        self.check_request(symbol.as_str(), start_time, end_time)?;
+        Ok(self.windows(start_time, end_time).collect())
+    }
+
+    /// `prices` of a bare coin like `BTC` against the quote asset, see `symbol_for_base`.
+    pub fn prices_for_base(&self, base: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<PriceSeries, PriceError> {
+        self.prices(self.symbol_for_base(base).as_str(), start_time, end_time)
+    }
+
+    /// `prices` with timestamps converted to `tz`, e.g. for regional dashboards.
+    /// Windows are still fetched in UTC.
+    pub fn prices_in_tz<S>(&self, symbol: S, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, tz: Tz) -> Result<Vec<LocalPricePoint>, PriceError>
+    where
+        S: TryInto<Symbol, Error = PriceError>,
+    {
+        Ok(self.prices(symbol, start_time, end_time)?.iter().map(|point| point.in_tz(tz)).collect())
+    }
+
+    /// Same as `prices` with the points sorted by `order`, reversed in place so no second series is allocated.
+    pub fn prices_ordered<S>(&self, symbol: S, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, order: Order) -> Result<PriceSeries, PriceError>
+    where
+        S: TryInto<Symbol, Error = PriceError>,
+    {
+        let symbol = symbol.try_into()?;
+        self.prices_for_symbol(&symbol, start_time, end_time, order)
+    }
+
+    #[tracing::instrument(level = "info", skip(self), fields(%symbol, %start_time, %end_time))]
+    fn prices_for_symbol(&self, symbol: &Symbol, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, order: Order) -> Result<PriceSeries, PriceError> {
+        let symbol = symbol.as_str();
+        self.check_request(symbol, start_time, end_time)?;
+        let retry_budget = self.retry_budget();
+        let mut prices = Vec::new();
+        for (window_start, window_end) in self.windows(start_time, end_time) {
+            let maybe_price = self.fetch_avg_price_for_window(symbol, &window_start, &window_end, &retry_budget)?;
+            if let Some(avg_price) = maybe_price {
+                prices.push(PricePoint { timestamp: window_start, price: avg_price });
+            }
+        }
+        if order == Order::Descending {
+            prices.reverse();
+        }
+        tracing::info!(points = prices.len(), "Fetched prices");
+        Ok(prices)
+    }
+
+    /// One point per trade, at its `T`, paging through aggregate trades by id from `from_id`
+    /// rather than by time so no trade on a window boundary is missed.
+    ///
+    /// Pages of `AGG_TRADES_PAGE_LIMIT` trades are requested, each continuing at the last
+    /// id + 1, until `max_trades` points are collected or Binance has no more trades.
+    pub fn prices_by_id(&self, symbol: &str, from_id: i64, max_trades: usize) -> Result<PriceSeries, PriceError> {
+        let symbol = self.check_symbol(symbol)?;
+        let symbol = symbol.as_str();
+        let mut prices = Vec::new();
+        let mut from_id = from_id;
+        while prices.len() < max_trades {
+            let api_response = self.binance_api.agg_trades(symbol, Some(from_id), None, None, Some(Self::AGG_TRADES_PAGE_LIMIT))
+                .map_err(|err| PriceError::from_api_error(symbol, err))?;
+            let trades = decode_agg_trades(symbol, &api_response, self.schema_mode)?;
+            let Some(last) = trades.last() else { break };
+            from_id = last.id + 1;
+            for trade in trades.iter().take(max_trades - prices.len()) {
+                let timestamp = DateTime::from_timestamp_millis(trade.time)
+                    .ok_or(PriceError::InvalidTradeTime(trade.time))?;
+                prices.push(PricePoint { timestamp, price: trade.price });
+            }
+        }
+        Ok(prices)
+    }
+
+    /// Same windows as `prices` but keeping the min, max, number of trades and average
+    /// trade size of each window besides the average. Empty windows are skipped.
+    pub fn price_stats(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<Vec<PricePointStats>> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let retry_budget = self.retry_budget();
+        let mut stats = Vec::new();
+        for (window_start, window_end) in self.windows(start_time, end_time) {
+            let api_response = self.fetch_agg_trades_for_window(symbol, &window_start, &window_end, &retry_budget)?;
+            let trades = trades(symbol, &api_response, self.schema_mode)?;
+            stats.extend(PricePointStats::from_trades(window_start, &trades));
+        }
+        Ok(stats)
+    }
+
+    /// Same as `prices` but pairing each window price with the number of trades averaged,
+    /// so consumers can weight down thin windows. Empty windows are skipped.
+    pub fn prices_with_counts(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<Vec<(PricePoint, usize)>> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let retry_budget = self.retry_budget();
+        let mut prices = Vec::new();
+        for (window_start, window_end) in self.windows(start_time, end_time) {
+            let api_response = self.fetch_agg_trades_for_window(symbol, &window_start, &window_end, &retry_budget)?;
+            let response_prices = trade_prices(symbol, &api_response, self.schema_mode)?;
+            if let Some(price) = mean(&response_prices) {
+                prices.push((PricePoint { timestamp: window_start, price }, response_prices.len()));
+            }
+        }
+        Ok(prices)
+    }
+
+    /// Fetches `prices` for each symbol, keyed by symbol.
+    /// Fails fast: the first symbol that errors aborts the call and its error is returned.
+    pub fn prices_for_symbols(&self, symbols: &[&str], start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<HashMap<String, PriceSeries>> {
+        symbols.iter()
+            .map(|symbol| {
+                let prices = self.prices(*symbol, start_time, end_time)
+                    .with_context(|| format!("Failed to fetch prices for {}", symbol))?;
+                Ok((symbol.to_string(), prices))
+            })
+            .collect()
+    }
+
+    /// Fetches `prices` for up to `concurrency` symbols at once, keyed by symbol.
+    ///
+    /// Unlike `prices_for_symbols` each symbol gets its own result, a failing symbol
+    /// doesn't abort the others. Requests still go through the shared API client, so
+    /// its `RateLimiter`, if any, keeps the workers within the weight budget.
+    pub fn prices_for_symbols_parallel(&self, symbols: &[&str], start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, concurrency: usize) -> HashMap<String, Result<PriceSeries, PriceError>> {
+        for_each_parallel(symbols, concurrency, |symbol| self.prices(symbol, start_time, end_time))
+    }
+
+    /// Latest price of each base asset of `watchlist`, keyed by its symbol in the quote asset,
+    /// fetching up to `concurrency` symbols at once.
+    ///
+    /// Like `prices_for_symbols_parallel` each symbol gets its own result, only failing
+    /// to read the watchlist fails the call.
+    pub fn latest_for_watchlist(&self, watchlist: &dyn Watchlist, defaults: &[&str], concurrency: usize) -> anyhow::Result<HashMap<String, anyhow::Result<f64>>> {
+        let symbols: Vec<String> = watchlist.tokens_or_defaults(defaults)?
+            .iter()
+            .map(|token| self.symbol_for_base(token))
+            .collect();
+        let symbols: Vec<&str> = symbols.iter().map(String::as_str).collect();
+        Ok(for_each_parallel(&symbols, concurrency, |symbol| self.latest_price(symbol)))
+    }
+
+    /// Price of `numer` in units of `denom` at each window both have prices for,
+    /// e.g. ETH/BTC from ETHUSDT and BTCUSDT.
+    ///
+    /// Windows where only one of them traded, or where `denom` averaged zero, are skipped.
+    pub fn ratio_series(&self, numer: &str, denom: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        let numer_prices = self.prices(numer, start_time, end_time)
+            .with_context(|| format!("Failed to fetch prices for {}", numer))?;
+        let denom_prices: HashMap<DateTime<Utc>, Decimal> = self.prices(denom, start_time, end_time)
+            .with_context(|| format!("Failed to fetch prices for {}", denom))?
+            .into_iter()
+            .map(|point| (point.timestamp, point.price))
+            .collect();
+        Ok(numer_prices.into_iter()
+            .filter_map(|point| {
+                let denom_price = denom_prices.get(&point.timestamp).filter(|price| !price.is_zero())?;
+                Some(PricePoint { timestamp: point.timestamp, price: point.price / denom_price })
+            })
+            .collect())
+    }
+
+    /// Same as `prices` but windows without trades are filled according to `fill_policy`,
+    /// so the series can be evenly spaced.
+    pub fn prices_with_fill(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, fill_policy: FillPolicy) -> Result<PriceSeries, PriceError> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let retry_budget = self.retry_budget();
+        let window_prices = self.windows(start_time, end_time)
+            .map(|(window_start, window_end)| {
+                Ok((window_start, self.fetch_avg_price_for_window(symbol, &window_start, &window_end, &retry_budget)?))
+            })
+            .collect::<Result<Vec<_>, PriceError>>()?;
+        Ok(fill_gaps(window_prices, fill_policy))
+    }
+
+    /// Fetches `prices` for the range and writes them to `store` in a single batch
+    /// before returning them. Nothing is stored if the fetch fails.
+    pub fn fetch_and_store(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, store: &dyn PriceStore) -> anyhow::Result<PriceSeries> {
+        let symbol = Symbol::new(symbol)?;
+        let prices = self.prices(&symbol, start_time, end_time)?;
+        store.store_prices(symbol.as_str(), &prices)?;
+        Ok(prices)
+    }
+
+    /// Prices since the previous call for `symbol` up to `end_time` (exclusive),
+    /// so consecutive calls tile the timeline without gaps or overlaps.
+    ///
+    /// Starts where `progress` says the last fetch ended, or `DEFAULT_INCREMENTAL_LOOKBACK`
+    /// before `end_time` the first time. The gap is fetched in chunks of at most
+    /// `max_windows` windows and the marker is moved past each chunk once it succeeded,
+    /// so an old marker catches up instead of failing with `TooManyWindows`.
+    ///
+    /// Fails if the first chunk fails. A later failure ends the call early with the
+    /// prices fetched so far, the next call resumes from the failed chunk.
+    pub fn prices_incremental(&self, symbol: &str, progress: &dyn FetchProgress, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        let symbol = Symbol::new(symbol)?;
+        let symbol = symbol.as_str();
+        let mut chunk_start = progress.get_last_fetched(symbol)?
+            .unwrap_or(*end_time - Self::DEFAULT_INCREMENTAL_LOOKBACK);
+        let max_span = self.bucketing.max_span(self.max_windows);
+        let mut prices = Vec::new();
+        let mut first_chunk = true;
+        while chunk_start < *end_time - Duration::milliseconds(1) {
+            let chunk_end = std::cmp::min(chunk_start + max_span, *end_time);
+            match self.prices(symbol, &chunk_start, &(chunk_end - Duration::milliseconds(1))) {
+                Ok(chunk) => prices.extend(chunk),
+                Err(err) if first_chunk => return Err(err.into()),
+                Err(err) => {
+                    tracing::warn!(symbol, %chunk_start, error = %err, "Incremental fetch stopped early");
+                    break;
+                }
+            }
+            progress.set_last_fetched(symbol, &chunk_end)?;
+            chunk_start = chunk_end;
+            first_chunk = false;
+        }
+        Ok(prices)
+    }
+
+    /// Prices for the range, fetching from Binance only the windows missing from `cache`.
+    ///
+    /// Consecutive missing windows are fetched as one sub-range and stored back before
+    /// the merged series is returned. Windows without trades are never cached, so they
+    /// are requested again on every call.
+    pub fn prices_smart(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, cache: &dyn PriceCache) -> anyhow::Result<PriceSeries> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let mut prices = cache.read_cached_prices(symbol, start_time, end_time)?;
+        let cached: HashSet<DateTime<Utc>> = prices.iter().map(|point| point.timestamp).collect();
+
+        let mut missing_ranges: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+        let mut previous_missing = false;
+        for (window_start, window_end) in self.windows(start_time, end_time) {
+            let missing = !cached.contains(&window_start);
+            match missing_ranges.last_mut() {
+                Some((_, range_end)) if missing && previous_missing => *range_end = window_end,
+                _ if missing => missing_ranges.push((window_start, window_end)),
+                _ => {}
+            }
+            previous_missing = missing;
+        }
+
+        for (range_start, range_end) in missing_ranges {
+            let fetched = self.prices(symbol, &range_start, &range_end)?;
+            cache.cache_prices(symbol, &fetched)?;
+            prices.extend(fetched);
+        }
+        prices.retain(|point| point.timestamp >= *start_time && point.timestamp <= *end_time);
+        prices.sort_by_key(|point| point.timestamp);
+        Ok(prices)
+    }
+
+    /// Price of the most recent trade.
+    pub fn latest_price(&self, symbol: &str) -> anyhow::Result<f64> {
+        let symbol = self.check_symbol(symbol)?;
+        let symbol = symbol.as_str();
+        let api_response = self.binance_api.agg_trades(symbol, None, None, None, Some(1))?;
+        let trades = decode_agg_trades(symbol, &api_response, self.schema_mode)?;
+        let trade = trades.last().with_context(|| format!("No recent trades for {}", symbol))?;
+        Ok(to_f64(trade.price)?)
+    }
+
+    /// Midpoint between the best bid and the best ask, fails when either side is empty.
+    pub fn mid_price(&self, symbol: &str) -> anyhow::Result<f64> {
+        let symbol = self.check_symbol(symbol)?;
+        let symbol = symbol.as_str();
+        let order_book: OrderBook = serde_json::from_str(&self.binance_api.depth(symbol, Some(Self::MID_PRICE_DEPTH))?)?;
+        let (best_bid, _) = order_book.bids.first().with_context(|| format!("No bids for {}", symbol))?;
+        let (best_ask, _) = order_book.asks.first().with_context(|| format!("No asks for {}", symbol))?;
+        Ok((best_bid + best_ask) / 2.0)
+    }
+
+    /// Binance's rolling average price over the last few minutes, a cheaper "price right
+    /// now" than reconstructing it from aggTrades.
+    pub fn current_avg(&self, symbol: &str) -> anyhow::Result<f64> {
+        let symbol = self.check_symbol(symbol)?;
+        let symbol = symbol.as_str();
+        let avg_price: AvgPrice = serde_json::from_str(&self.binance_api.avg_price(symbol)?)?;
+        Ok(avg_price.price.parse::<f64>().map_err(|_| PriceError::InvalidPrice(avg_price.price.clone()))?)
+    }
+
+    /// How far Binance's clock is ahead of ours (negative when behind).
+    ///
+    /// Our clock is read before and after the request and the midpoint is used, so the
+    /// offset isn't skewed by the request latency. Warns when it exceeds `CLOCK_SKEW_WARNING`.
+    pub fn clock_offset(&self) -> anyhow::Result<Duration> {
+        let sent = Utc::now();
+        let server_time = self.binance_api.server_time()?;
+        let received = Utc::now();
+        let server_time = DateTime::from_timestamp_millis(server_time)
+            .with_context(|| format!("Invalid server time {}", server_time))?;
+        let offset = server_time - (sent + (received - sent) / 2);
+        if offset.abs() > Self::CLOCK_SKEW_WARNING {
+            tracing::warn!(offset_ms = offset.num_milliseconds(), "Local clock is off from Binance");
+        }
+        Ok(offset)
+    }
+
+    /// Last traded price from the 24hr ticker.
+    pub fn latest(&self, symbol: &str) -> anyhow::Result<f64> {
+        let symbol = self.check_symbol(symbol)?;
+        let symbol = symbol.as_str();
+        let api_response = self.binance_api.ticker_24hr(symbol)?;
+        let ticker: Ticker24hr = serde_json::from_str(&api_response)?;
+        Ok(ticker.last_price.parse::<f64>().map_err(|_| PriceError::InvalidPrice(ticker.last_price.clone()))?)
+    }
+
+    /// Mean of the window prices over the range, `None` when there are no prices.
+    pub fn average_price(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<Option<f64>> {
+        let prices = self.prices(symbol, start_time, end_time)?;
+        if prices.is_empty() {
+            return Ok(None);
+        }
+        let average = prices.iter().map(|point| point.price).sum::<Decimal>() / Decimal::from(prices.len());
+        Ok(average.to_f64())
+    }
+
+    /// Compares the latest price against the average over the trailing `lookback`.
+    ///
+    /// The average is computed from windowed prices. When `lookback` needs more than
+    /// `max_windows` of the configured windows, larger fixed windows splitting it into
+    /// `max_windows` are used instead, so a long lookback makes at most that many requests.
+    pub fn price_vs_average(&self, symbol: &str, lookback: Duration) -> anyhow::Result<PriceComparison> {
+        let end_time = Utc::now();
+        let start_time = end_time - lookback;
+        let bucketing = if self.bucketing.window_count(&start_time, &end_time) <= self.max_windows {
+            self.bucketing
+        } else {
+            let window_millis = (lookback.num_milliseconds() + self.max_windows - 1) / self.max_windows;
+            Bucketing::Fixed(WindowSize::milliseconds(window_millis)?)
+        };
+        let average = self.average_over(symbol, &start_time, &end_time, bucketing)?
+            .with_context(|| format!("No prices for {} in the last {}", symbol, lookback))?;
+        let latest = self.latest_price(symbol)?;
+        anyhow::ensure!(average != 0.0, "Average price of {} is zero", symbol);
+        Ok(PriceComparison { latest, average, pct_diff: (latest - average) / average * 100.0 })
+    }
+
+    /// Mean of the window prices of `bucketing` over the range, `None` when there are no prices.
+    fn average_over(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, bucketing: Bucketing) -> Result<Option<f64>, PriceError> {
+        PriceError::check_range(start_time, end_time)?;
+        let symbol = self.check_symbol(symbol)?;
+        let symbol = symbol.as_str();
+        let retry_budget = self.retry_budget();
+        let mut prices = Vec::new();
+        for (window_start, window_end) in bucketing.windows(start_time, end_time) {
+            prices.extend(self.fetch_avg_price_for_window(symbol, &window_start, &window_end, &retry_budget)?);
+        }
+        Ok(mean(&prices).and_then(|average| average.to_f64()))
+    }
+
+    /// Number of windows in the range that have at least one trade, i.e. the number
+    /// of points `prices` would return.
+    ///
+    /// Probes with klines instead of aggTrades so one request covers up to 1000 minutes
+    /// (or seconds when windows aren't minute aligned) instead of a single window.
+    /// A kline is attributed to the window its open time falls in.
+    pub fn count_active_windows(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<usize> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let window_starts: Vec<i64> = self.windows(start_time, end_time)
+            .map(|(window_start, _)| window_start.timestamp_millis())
+            .collect();
+        let interval = if window_starts.iter().all(|start| start % 60_000 == 0) {
+            Interval::OneMinute
+        } else {
+            Interval::OneSecond
+        };
+
+        let mut active_windows = HashSet::new();
+        let mut page_start = start_time.timestamp_millis();
+        loop {
+            let api_response = self.binance_api.klines(
+                symbol,
+                interval.as_str(),
+                Some( page_start ),
+                Some( end_time.timestamp_millis() ),
+                Some( KlinePriceProvider::KLINES_LIMIT ))?;
+            let klines: KlinesResponse = serde_json::from_str(&api_response)?;
+            let page_len = klines.len() as i64;
+
+            for kline in klines {
+                page_start = kline.open_time + 1;
+                let window = window_starts.partition_point(|start| *start <= kline.open_time);
+                if kline.number_of_trades > 0 && window > 0 {
+                    active_windows.insert(window - 1);
+                }
+            }
+
+            if page_len < KlinePriceProvider::KLINES_LIMIT {
+                break;
+            }
+        }
+        Ok(active_windows.len())
+    }
+
+    /// Volume weighted average price of every trade in the range, `sum(p*q) / sum(q)`.
+    /// Unlike `average_price` busy windows weigh more than quiet ones.
+    /// `None` when there are no trades.
+    ///
+    /// Windows with more trades than one page are paged through by id, so none are left out.
+    pub fn range_vwap(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<Option<f64>> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let retry_budget = self.retry_budget();
+        let mut notional = Decimal::ZERO;
+        let mut quantity = Decimal::ZERO;
+        for (window_start, window_end) in self.windows(start_time, end_time) {
+            let trades = self.fetch_all_agg_trades_for_window(symbol, &window_start, &window_end, &retry_budget)?;
+            let (window_notional, window_quantity) = notional_and_quantity(&trades);
+            notional += window_notional;
+            quantity += window_quantity;
+        }
+        if quantity > Decimal::ZERO { Ok((notional / quantity).to_f64()) } else { Ok(None) }
+    }
+
+    /// Windows with the highest and lowest average price over the range as `(max, min)`,
+    /// `None` when there are no prices. Ties keep the earliest window.
+    pub fn extremes(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<Option<(PricePoint, PricePoint)>> {
+        let prices = self.prices(symbol, start_time, end_time)?;
+        let mut points = prices.into_iter();
+        let Some(first) = points.next() else {
+            return Ok(None);
+        };
+        let (max, min) = points.fold((first.clone(), first), |(max, min), point| {
+            if point.price > max.price {
+                (point, min)
+            } else if point.price < min.price {
+                (max, point)
+            } else {
+                (max, min)
+            }
+        });
+        Ok(Some((max, min)))
+    }
+
+    /// Same as `prices` but fetches up to `concurrency` windows at once.
+    ///
+    /// Windows are independent so they are handed out to a fixed set of scoped threads.
+    /// The resulting series is sorted by timestamp regardless of completion order.
+    /// On failure the remaining windows are abandoned and the error of the earliest
+    /// failed window is returned.
+    pub fn prices_parallel(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>, concurrency: usize) -> Result<PriceSeries, PriceError> {
+        let symbol = self.check_request(symbol, start_time, end_time)?;
+        let symbol = symbol.as_str();
+        let windows: Vec<(DateTime<Utc>, DateTime<Utc>)> = self.windows(start_time, end_time).collect();
+        let next_window = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let retry_budget = self.retry_budget();
+        let results = Mutex::new(Vec::with_capacity(windows.len()));
+
+        std::thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, windows.len().max(1)) {
+                scope.spawn(|| {
+                    while !failed.load(Ordering::Relaxed) {
+                        let Some((window_start, window_end)) = windows.get(next_window.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        let result = self.fetch_avg_price_for_window(symbol, window_start, window_end, &retry_budget);
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        results.lock().unwrap().push((*window_start, result));
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(window_start, _)| *window_start);
+
+        let mut prices = Vec::with_capacity(results.len());
+        for (window_start, result) in results {
+            if let Some(avg_price) = result? {
+                prices.push(PricePoint { timestamp: window_start, price: avg_price });
+            }
+        }
+        Ok(prices)
+    }
+}
+
+impl PriceProvider for BinancePriceProvider {
+    fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+        Ok(BinancePriceProvider::prices(self, symbol, start_time, end_time)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use binance_price_provider::binance_api::BinanceHttpClient;
+    use binance_price_provider::mock_binance_api::MockBinanceAPI;
+    use mockall::predicate::*;
+    extern crate assert_float_eq;
+    use assert_float_eq::assert_float_absolute_eq;
+    use chrono::prelude::*;
+    use rust_decimal_macros::dec;
+    use std::sync::LazyLock;
+
+    // TODO: since creating a test api with mockito is lightweight this tests need improvement
+    // Use a mocked API here and integrate the BinanceApi component into the whole test suite
+    // This will improve test quality and allow further TDD'ing
+
+    const SYMBOL: &str = "BTCUSDC";
+
+    const SINGLE_PRICE_RESPONSE: &str = r#"[{"a": 26129,"p": "0.01633102","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
+    const MULTIPLE_PRICES_RESPONSE: &str = concat!(
+        r#"[{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "2.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "3.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#
+    );
+    /// Out of time order, the last two trades share `T` and are ordered by `a`
+    const VARIED_TIMES_RESPONSE: &str = concat!(
+        r#"[{"a": 26130,"p": "2.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709200,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26132,"p": "4.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709300,"m": true,"M": true },"#,
+        r#"{"a": 26131,"p": "3.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709300,"m": true,"M": true }]"#
+    );
+    const MULTIPLE_PRICES_RESPONSE_2: &str = concat!(
+        r#"[{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
+        r#"{"a": 26129,"p": "2.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#,
+    );
+    const MISSING_PRICE_RESPONSE: &str = r#"[{"a": 26129,"q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
+    const INVALID_PRICE_RESPONSE: &str = r#"[{"a": 26129,"p": "notafloat","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
+
+    // Default time values spaning just one time window
+    static START_TIME: LazyLock<DateTime<Utc>> = LazyLock::new( || 
+        Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap() );
+    static END_TIME: LazyLock<DateTime<Utc>> = LazyLock::new( || 
+        *START_TIME + BinancePriceProvider::TIME_WINDOW - Duration::seconds(1) );    
+
+    #[test]
+    fn test_can_create_a_binance_price_provider() {
+        let mock_api = MockBinanceAPI::new();
+        let _binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+    }
+
+    #[test]
+    fn test_binance_provider_returns_empty_when_no_prices() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL), 
+                always(), 
+                always(), 
+                always(), 
+                always())
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
+
+        assert!( prices.is_ok() );
+        assert!( prices.unwrap().is_empty() );
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_binance_provider_warns_about_windows_without_trades() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
+
+        assert!( logs_contain("WARN") );
+        assert!( logs_contain("No trades in window") );
+        assert!( logs_contain(SYMBOL) );
+    }
+
+    #[test]
+    fn test_binance_provider_rejects_reversed_range() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades().never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let err = binance_provider.prices(SYMBOL, &END_TIME, &START_TIME).unwrap_err();
+
+        assert!( matches!(err, PriceError::InvalidRange { .. }) );
+        assert!( err.to_string().contains(&START_TIME.to_string()) );
+        assert!( err.to_string().contains(&END_TIME.to_string()) );
+    }
+
+    #[test]
+    fn test_binance_provider_allows_range_at_max_windows() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(10)
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_max_windows(10);
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 10;
+
+        assert!( binance_provider.prices(SYMBOL, &START_TIME, &end_time).is_ok() );
+    }
+
+    #[test]
+    fn test_binance_provider_rejects_range_over_max_windows() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades().never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_max_windows(10);
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 10 + Duration::milliseconds(1);
+        let err = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap_err();
+
+        assert!( matches!(err, PriceError::TooManyWindows { count: 11, max: 10 }) );
+        assert!( err.to_string().contains("11 windows") );
+    }
+
+    #[test]
+    fn test_binance_provider_rejects_empty_range() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades().never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        assert!( matches!(binance_provider.prices(SYMBOL, &START_TIME, &START_TIME), Err(PriceError::InvalidRange { .. })) );
+        assert!( binance_provider.price_stats(SYMBOL, &START_TIME, &START_TIME).is_err() );
+        assert!( binance_provider.prices_parallel(SYMBOL, &START_TIME, &START_TIME, 2).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_price_if_just_one_price() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL), 
+                always(), 
+                always(), 
+                always(), 
+                always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
+        
+        assert_eq!( prices.len(), 1 );
+        assert_eq!( prices[0].price, dec!(0.01633102) );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_error_on_api_error() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
+        
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_error_on_missing_price_data() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(MISSING_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_error_on_non_numeric_price_data() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(INVALID_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_average_price_from_single_time_window() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL), 
+                always(), 
+                eq(Some(START_TIME.timestamp_millis())), 
+                eq(Some(END_TIME.timestamp_millis())), 
+                always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
+
+        assert_eq!( prices.len(), 1 );
+        assert_eq!( prices[0].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[0].timestamp, *START_TIME );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_average_prices_from_multiple_time_windows() {
+        // ensure to capture just 2 windows
+        let first_window_end = *START_TIME + BinancePriceProvider::TIME_WINDOW;
+        let end_time = first_window_end + Duration::seconds(1);
+
+        let mut mock_api = MockBinanceAPI::new();
+        // call #1
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL), 
+                always(), 
+                eq(Some(START_TIME.timestamp_millis())), 
+                eq(Some(first_window_end.timestamp_millis()-1)), 
+                always() )
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        // call #2
+        mock_api.expect_agg_trades()
+        .times(1)
+        .with(
+            eq(SYMBOL), 
+            always(), 
+            eq(Some(first_window_end.timestamp_millis())), 
+            eq(Some(end_time.timestamp_millis())), 
+            always() )
+        .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
+        
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[0].timestamp, *START_TIME );
+        assert_eq!( prices[1].price, dec!(1.5) );
+        assert_eq!( prices[1].timestamp, first_window_end );
+    }
+
+    #[test]
+    fn test_binance_provider_skips_time_windows_with_no_prices() {
+       // capture 3 windows
+       let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+       let mut mock_api = MockBinanceAPI::new();
+       // call #1
+       mock_api.expect_agg_trades()
+           .times(1)
+           .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        // call #2
+        mock_api.expect_agg_trades()
+        .times(1)
+        .returning(|_,_,_,_,_| Ok("[]".to_string()));
+        // call #3
+        mock_api.expect_agg_trades()
+           .times(1)
+           .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
+        
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[0].timestamp, *START_TIME );
+        assert_eq!( prices[1].price, dec!(1.5) );
+        assert_eq!( prices[1].timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW * 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_prices_for_given_symbol() {
+        const NEW_SYMBOL: &str = "ETHUSDT";
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(NEW_SYMBOL),
+                always(),
+                always(),
+                always(),
+                always(),
+            )
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let _ = binance_provider.prices(NEW_SYMBOL, &START_TIME, &END_TIME);
+    }
+
+    #[test]
+    fn test_binance_provider_prices_partial_keeps_going_past_failed_windows() {
+        let second_window = *START_TIME + BinancePriceProvider::TIME_WINDOW;
+        let end_time = *START_TIME + Duration::minutes(3);
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(3)
+            .returning(move |_,_,start,_,_| if start == Some(second_window.timestamp_millis()) {
+                Err(anyhow::Error::msg("connection refused"))
+            } else {
+                Ok(SINGLE_PRICE_RESPONSE.to_string())
+            });
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let (prices, failures) = binance_provider.prices_partial(SYMBOL, &START_TIME, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].timestamp, *START_TIME );
+        assert_eq!( prices[1].timestamp, *START_TIME + Duration::minutes(2) );
+        assert_eq!( failures.len(), 1 );
+        assert_eq!( failures[0].0, second_window );
+        assert!( matches!(failures[0].1, PriceError::Http(_)) );
+    }
+
+    #[test]
+    fn test_binance_provider_plan_lists_windows_without_fetching() {
+        let first_window_end = *START_TIME + BinancePriceProvider::TIME_WINDOW;
+        let end_time = *START_TIME + Duration::minutes(3);
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades().never();
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        let plan = binance_provider.plan(SYMBOL, &START_TIME, &end_time).unwrap();
+
+        assert_eq!( plan.len(), 3 );
+        assert_eq!( plan[0], (*START_TIME, first_window_end - Duration::milliseconds(1)) );
+        assert_eq!( plan[1].0, first_window_end );
+        assert_eq!( plan[2], (*START_TIME + Duration::minutes(2), end_time - Duration::milliseconds(1)) );
+        assert!( binance_provider.plan(SYMBOL, &end_time, &START_TIME).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_plan_rejects_unknown_symbol_like_prices() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .returning(|| Ok(EXCHANGE_INFO_RESPONSE.to_string()));
+        mock_api.expect_agg_trades().never();
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_validate_symbols(true);
+
+        assert_eq!( binance_provider.plan(SYMBOL, &START_TIME, &END_TIME).unwrap().len(), 1 );
+        let result = binance_provider.plan("BTCUSCD", &START_TIME, &END_TIME);
+        assert!( matches!(result, Err(PriceError::UnknownSymbol(symbol)) if symbol == "BTCUSCD") );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_by_id_continues_after_last_trade_id() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(Some(10)), eq(None), eq(None), eq(Some(1000)))
+            .returning(|_,_,_,_,_| Ok(concat!(
+                r#"[{"a": 10,"p": "1.0","q": "1.0","f": 1,"l": 1,"T": 1498793709153,"m": true,"M": true },"#,
+                r#"{"a": 11,"p": "2.0","q": "1.0","f": 2,"l": 2,"T": 1498793709154,"m": true,"M": true }]"#
+            ).to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(Some(12)), eq(None), eq(None), eq(Some(1000)))
+            .returning(|_,_,_,_,_| Ok(concat!(
+                r#"[{"a": 12,"p": "3.0","q": "1.0","f": 3,"l": 3,"T": 1498793709155,"m": true,"M": true },"#,
+                r#"{"a": 13,"p": "4.0","q": "1.0","f": 4,"l": 4,"T": 1498793709156,"m": true,"M": true }]"#
+            ).to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_by_id(SYMBOL, 10, 3).unwrap();
+
+        assert_eq!( prices.iter().map(|point| point.price).collect::<Vec<_>>(), vec![dec!(1.0), dec!(2.0), dec!(3.0)] );
+        assert_eq!( prices[2].timestamp, DateTime::from_timestamp_millis(1498793709155).unwrap() );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_by_id_stops_when_no_more_trades() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(Some(26129)), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(Some(26130)), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        assert_eq!( binance_provider.prices_by_id(SYMBOL, 26129, 10).unwrap().len(), 1 );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_by_id_rejects_out_of_range_trade_time() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(r#"[{"a": 10,"p": "1.0","q": "1.0","f": 1,"l": 1,"T": 9223372036854775807,"m": true,"M": true }]"#.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let err = binance_provider.prices_by_id(SYMBOL, 10, 1).unwrap_err();
+
+        assert!( matches!(err, PriceError::InvalidTradeTime(9223372036854775807)), "{}", err );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_with_counts_counts_trades_per_window() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+        let mut mock_api = MockBinanceAPI::new();
+        let mut seq = mockall::Sequence::new();
+        for response in [MULTIPLE_PRICES_RESPONSE, "[]", SINGLE_PRICE_RESPONSE] {
+            mock_api.expect_agg_trades()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(move |_,_,_,_,_| Ok(response.to_string()));
+        }
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_with_counts(SYMBOL, &START_TIME, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[0].0.price, dec!(7) / dec!(3) );
+        assert_eq!( prices[0].1, 3 );
+        assert_eq!( prices[1].0.timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW * 2 );
+        assert_eq!( prices[1].1, 1 );
+    }
+
+    #[test]
+    fn test_price_series_json_round_trip() {
+        let series = vec![
+            PricePoint { timestamp: *START_TIME, price: dec!(1.5) },
+            PricePoint { timestamp: *START_TIME + BinancePriceProvider::TIME_WINDOW, price: dec!(0.01633102) },
+        ];
+
+        let json = serde_json::to_string(&series).unwrap();
+        assert_eq!( json, r#"[{"timestamp":"2025-01-27T14:00:00Z","price":1.5},{"timestamp":"2025-01-27T14:01:00Z","price":0.01633102}]"# );
+
+        let parsed: PriceSeries = serde_json::from_str(&json).unwrap();
+        assert_eq!( parsed, series );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_in_aligned_range_starts_windows_on_boundaries() {
+        let mut mock_api = MockBinanceAPI::new();
+        for minute in 0..3 {
+            let window_start = Utc.with_ymd_and_hms(2025, 1, 27, 14, minute, 0).unwrap().timestamp_millis();
+            mock_api.expect_agg_trades()
+                .times(1)
+                .with(eq(SYMBOL), always(), eq(Some(window_start)), eq(Some(window_start + 59_999)), always())
+                .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        }
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let range = TimeRange::new(
+            Utc.with_ymd_and_hms(2025, 1, 27, 14, 0, 37).unwrap(),
+            Utc.with_ymd_and_hms(2025, 1, 27, 14, 2, 10).unwrap(),
+        ).unwrap();
+
+        let prices = binance_provider.prices_in_range(SYMBOL, &range.aligned_to(Duration::minutes(1))).unwrap();
+
+        assert_eq!( prices.len(), 3 );
+        assert_eq!( prices[0].timestamp, Utc.with_ymd_and_hms(2025, 1, 27, 14, 0, 0).unwrap() );
+    }
+
+    #[test]
+    fn test_binance_provider_aggregation_picks_first_and_last_trades() {
+        let price_with = |aggregation| {
+            let mut mock_api = MockBinanceAPI::new();
+            mock_api.expect_agg_trades()
+                .times(1)
+                .returning(|_,_,_,_,_| Ok(VARIED_TIMES_RESPONSE.to_string()));
+            let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_aggregation(aggregation);
+            binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap()[0].price
+        };
+
+        assert_eq!( price_with(Aggregation::First), dec!(1.0) );
+        assert_eq!( price_with(Aggregation::Last), dec!(4.0) );
+        assert_eq!( price_with(Aggregation::Mean), dec!(2.75) );
+        assert_eq!( price_with(Aggregation::Max), dec!(4.0) );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_for_base_appends_quote_asset() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_quote_asset(" usdc");
+
+        assert_eq!( binance_provider.symbol_for_base(" btc "), "BTCUSDC" );
+        assert_eq!( binance_provider.prices_for_base("btc", &START_TIME, &END_TIME).unwrap().len(), 1 );
+    }
+
+    #[test]
+    fn test_binance_provider_symbol_for_base_defaults_to_usdt() {
+        let binance_provider = BinancePriceProvider::new(Box::new(MockBinanceAPI::new()));
+
+        assert_eq!( binance_provider.symbol_for_base("eth"), "ETHUSDT" );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_in_tz_converts_to_local_time() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        let prices = binance_provider.prices_in_tz(SYMBOL, &START_TIME, &END_TIME, chrono_tz::America::New_York).unwrap();
+
+        assert_eq!( prices.len(), 1 );
+        assert_eq!( prices[0].timestamp.to_rfc3339(), "2025-01-27T09:00:00-05:00" );
+        assert_eq!( prices[0].price, dec!(0.01633102) );
+    }
+
+    #[test]
+    fn test_price_point_in_tz_follows_dst() {
+        let point = PricePoint { timestamp: Utc.with_ymd_and_hms(2025,7,1,14,0,0).unwrap(), price: dec!(1) };
+
+        assert_eq!( point.in_tz(chrono_tz::America::New_York).timestamp.to_rfc3339(), "2025-07-01T10:00:00-04:00" );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_ordered_descending_swaps_first_and_last() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(6)
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let start_time = Utc.with_ymd_and_hms(2025, 1, 27, 14, 0, 0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025, 1, 27, 14, 2, 59).unwrap();
+
+        let ascending = binance_provider.prices_ordered(SYMBOL, &start_time, &end_time, Order::Ascending).unwrap();
+        let descending = binance_provider.prices_ordered(SYMBOL, &start_time, &end_time, Order::Descending).unwrap();
+
+        assert_eq!( ascending.len(), 3 );
+        assert_eq!( ascending.first().unwrap().timestamp, start_time );
+        assert_eq!( descending.first().unwrap().timestamp, ascending.last().unwrap().timestamp );
+        assert_eq!( descending.last().unwrap().timestamp, ascending.first().unwrap().timestamp );
+        assert!( descending.windows(2).all(|pair| pair[0].timestamp > pair[1].timestamp) );
+    }
+
+    #[test]
+    fn test_binance_provider_clock_offset_against_server_time() {
+        let server_ahead = Duration::seconds(5);
+        let server_time = (Utc::now() + server_ahead).timestamp_millis();
+        let _m = mockito::mock("GET", "/api/v3/time")
+            .with_status(200)
+            .with_body(format!(r#"{{"serverTime": {}}}"#, server_time))
+            .create();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(BinanceHttpClient::new_with_test_endpoint()));
+        let offset = binance_provider.clock_offset().unwrap();
+
+        assert!( (offset - server_ahead).abs() < Duration::milliseconds(200), "offset {}", offset );
+    }
+
+    #[test]
+    fn test_binance_provider_mid_price_between_best_bid_and_ask() {
+        let _m = mockito::mock("GET", "/api/v3/depth")
+            .match_query(mockito::Matcher::UrlEncoded("symbol".into(), SYMBOL.into()))
+            .with_status(200)
+            .with_body(r#"{"lastUpdateId": 1, "bids": [["100.0", "1.0"], ["99.0", "2.0"]], "asks": [["101.0", "1.5"], ["102.0", "3.0"]]}"#)
+            .create();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(BinanceHttpClient::new_with_test_endpoint()));
+
+        assert_eq!( binance_provider.mid_price(SYMBOL).unwrap(), 100.5 );
+    }
+
+    #[test]
+    fn test_binance_provider_mid_price_fails_on_empty_book() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_depth()
+            .returning(|_,_| Ok(r#"{"lastUpdateId": 1, "bids": [], "asks": [["101.0", "1.5"]]}"#.to_string()));
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        assert!( binance_provider.mid_price(SYMBOL).unwrap_err().to_string().contains("No bids") );
+    }
+
+    #[test]
+    fn test_binance_provider_current_avg_parses_avg_price() {
+        let _m = mockito::mock("GET", "/api/v3/avgPrice")
+            .match_query(mockito::Matcher::UrlEncoded("symbol".into(), SYMBOL.into()))
+            .with_status(200)
+            .with_body(r#"{"mins": 5, "price": "9.35751834", "closeTime": 1694061154503}"#)
+            .create();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(BinanceHttpClient::new_with_test_endpoint()));
+
+        assert_float_absolute_eq!( binance_provider.current_avg(SYMBOL).unwrap(), 9.35751834 );
+        _m.assert();
+    }
+
+    #[test]
+    fn test_binance_provider_current_avg_rejects_non_numeric_price() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_avg_price()
+            .returning(|_| Ok(r#"{"mins": 5, "price": "notafloat"}"#.to_string()));
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        assert!( binance_provider.current_avg(SYMBOL).unwrap_err().to_string().contains("notafloat") );
+    }
+
+    #[test]
+    fn test_moving_average_smooths_trailing_windows() {
+        let window_start = |n: i32| *START_TIME + BinancePriceProvider::TIME_WINDOW * n;
+        let series: PriceSeries = [dec!(1), dec!(2), dec!(6), dec!(4), dec!(8)].into_iter().enumerate()
+            .map(|(i, price)| PricePoint { timestamp: window_start(i as i32), price })
+            .collect();
+
+        let smoothed = moving_average(&series, 3);
+
+        assert_eq!( smoothed.len(), 3 );
+        assert_eq!( smoothed[0].price, dec!(3) );
+        assert_eq!( smoothed[1].price, dec!(4) );
+        assert_eq!( smoothed[2].price, dec!(6) );
+        assert_eq!( smoothed.iter().map(|point| point.timestamp).collect::<Vec<_>>(), vec![window_start(2), window_start(3), window_start(4)] );
+    }
+
+    #[test]
+    fn test_moving_average_is_empty_for_zero_or_oversized_window() {
+        let series = vec![PricePoint { timestamp: *START_TIME, price: dec!(1) }];
+
+        assert!( moving_average(&series, 0).is_empty() );
+        assert!( moving_average(&series, 2).is_empty() );
+        assert_eq!( moving_average(&series, 1).len(), 1 );
+    }
+
+    #[test]
+    fn test_binance_provider_parallel_returns_timestamp_sorted_series() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+        let window_start = |n: i32| *START_TIME + BinancePriceProvider::TIME_WINDOW * n;
+
+        // windows may be fetched in any order, each one answers by its start time
+        let mut mock_api = MockBinanceAPI::new();
+        for (n, response) in [
+            (0, MULTIPLE_PRICES_RESPONSE),
+            (1, SINGLE_PRICE_RESPONSE),
+            (2, MULTIPLE_PRICES_RESPONSE_2),
+        ] {
+            mock_api.expect_agg_trades()
+                .times(1)
+                .with(
+                    eq(SYMBOL),
+                    always(),
+                    eq(Some(window_start(n).timestamp_millis())),
+                    always(),
+                    always())
+                .returning(move |_,_,_,_,_| Ok(response.to_string()));
+        }
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_parallel(SYMBOL, &START_TIME, &end_time, 3).unwrap();
+
+        assert_eq!( prices.len(), 3 );
+        assert_eq!( prices[0].timestamp, window_start(0) );
+        assert_eq!( prices[0].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[1].timestamp, window_start(1) );
+        assert_eq!( prices[1].price, dec!(0.01633102) );
+        assert_eq!( prices[2].timestamp, window_start(2) );
+        assert_eq!( prices[2].price, dec!(1.5) );
+    }
+
+    #[test]
+    fn test_binance_provider_parallel_returns_error_on_api_error() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.prices_parallel(SYMBOL, &START_TIME, &end_time, 2).is_err() );
+    }
+
+    const EXCHANGE_INFO_RESPONSE: &str = r#"{"symbols": [
+        {"symbol": "BTCUSDC", "status": "TRADING", "filters": [{"filterType": "PRICE_FILTER", "tickSize": "0.01000000"}]},
+        {"symbol": "ETHUSDT", "status": "TRADING", "filters": []}
+    ]}"#;
+
+    #[test]
+    fn test_binance_provider_uses_exchange_info_when_available() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .returning(|| Ok(EXCHANGE_INFO_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.is_valid_symbol(SYMBOL).unwrap() );
+        assert!( !binance_provider.is_valid_symbol("BTCUSCD").unwrap() );
+        assert_eq!( binance_provider.price_precision(SYMBOL).unwrap(), 2 );
+        assert_eq!( binance_provider.price_precision("ETHUSDT").unwrap(), DEFAULT_PRICE_PRECISION );
+    }
+
+    #[test]
+    fn test_binance_provider_fetches_exchange_info_once() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .times(1)
+            .returning(|| Ok(EXCHANGE_INFO_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.is_valid_symbol(SYMBOL).unwrap() );
+        assert!( binance_provider.is_valid_symbol("ETHUSDT").unwrap() );
+        assert_eq!( binance_provider.price_precision(SYMBOL).unwrap(), 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_validates_symbol_before_fetching_prices() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .returning(|| Ok(EXCHANGE_INFO_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_validate_symbols(true);
+
+        assert_eq!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap().len(), 1 );
+        let result = binance_provider.prices("BTCUSCD", &START_TIME, &END_TIME);
+        assert!( matches!(result, Err(PriceError::UnknownSymbol(symbol)) if symbol == "BTCUSCD") );
+    }
+
+    #[test]
+    fn test_binance_provider_errors_when_exchange_info_unavailable() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .returning(|| Err(anyhow::Error::msg("some error")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.is_valid_symbol(SYMBOL).is_err() );
+        assert!( binance_provider.price_precision(SYMBOL).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_reports_rate_limited_exchange_info() {
+        let _m = mockito::mock("GET", "/api/v3/exchangeInfo")
+            .with_status(429)
+            .create();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(BinanceHttpClient::new_with_test_endpoint()))
+            .with_validate_symbols(true);
+        let result = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
+
+        assert!( matches!(result, Err(PriceError::RateLimited)), "{:?}", result );
+    }
+
+    #[test]
+    fn test_binance_provider_falls_back_when_exchange_info_unavailable_and_permissive() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .returning(|| Err(anyhow::Error::msg("some error")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_exchange_info_fallback(ExchangeInfoFallback::Permissive);
+        assert!( binance_provider.is_valid_symbol(SYMBOL).unwrap() );
+        assert!( !binance_provider.is_valid_symbol("btc-usdc").unwrap() );
+        assert_eq!( binance_provider.price_precision(SYMBOL).unwrap(), DEFAULT_PRICE_PRECISION );
+    }
+
+    #[test]
+    fn test_binance_provider_extremes_returns_max_and_min_windows() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let (max, min) = binance_provider.extremes(SYMBOL, &START_TIME, &end_time).unwrap().unwrap();
+
+        assert_eq!( max.price, dec!(7) / dec!(3) );
+        assert_eq!( max.timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW );
+        assert_eq!( min.price, dec!(0.01633102) );
+        assert_eq!( min.timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW * 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_extremes_returns_none_when_no_prices() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.extremes(SYMBOL, &START_TIME, &END_TIME).unwrap().is_none() );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_decode_error_naming_field_on_non_numeric_price_data() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(INVALID_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let result = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
+        assert!( matches!(&result, Err(PriceError::Decode(err)) if err.to_string().contains(r#"invalid number "notafloat" in field `p`"#)) );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_unknown_symbol_error_on_invalid_symbol_body() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let err = binance_provider.prices("BTCUSCD", &START_TIME, &END_TIME).unwrap_err();
+        assert!( err.is_unknown_symbol() );
+        assert!( err.to_string().contains("-1121") );
+        assert!( err.to_string().contains("Invalid symbol") );
+    }
+
+    #[test]
+    fn test_binance_provider_surfaces_other_binance_error_bodies() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(r#"{"code":-1100,"msg":"Illegal characters found in parameter 'symbol'."}"#.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let err = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap_err();
+        assert!( matches!(&err, PriceError::Binance { code: -1100, .. }) );
+        assert!( !err.is_unknown_symbol() );
+        assert_eq!( err.to_string(), "Binance error -1100 for BTCUSDC: Illegal characters found in parameter 'symbol'." );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_decode_error_on_missing_price_data() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(MISSING_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let result = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
+        assert!( matches!(result, Err(PriceError::Decode(_))) );
+    }
+
+    #[test]
+    fn test_binance_provider_returns_http_error_on_api_error() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let result = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
+        assert!( matches!(result, Err(PriceError::Http(_))) );
+    }
+
+    const EXTRA_FIELD_RESPONSE: &str = r#"[{"a": 26129,"p": "0.01633102","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true,"X": "new"}]"#;
+
+    #[test]
+    fn test_binance_provider_ignores_unknown_fields_by_default() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(EXTRA_FIELD_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
+        assert_eq!( prices[0].price, dec!(0.01633102) );
+    }
+
+    #[test]
+    fn test_binance_provider_rejects_unknown_fields_in_strict_mode() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(EXTRA_FIELD_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_schema_mode(SchemaMode::Strict);
+        let result = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
+        assert!( matches!(result, Err(PriceError::Decode(err)) if err.to_string().contains("unknown field `X`")) );
+    }
+
+    #[test]
+    fn test_binance_provider_accepts_documented_fields_in_strict_mode() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_schema_mode(SchemaMode::Strict);
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
+        assert_eq!( prices[0].price, dec!(0.01633102) );
+    }
+
+    /// In-memory `PriceCache` recording what gets stored
+    #[derive(Default)]
+    struct InMemoryPriceCache {
+        prices: Mutex<Vec<PricePoint>>,
+        stored: Mutex<Vec<PricePoint>>,
+    }
+
+    impl PriceCache for InMemoryPriceCache {
+        fn read_cached_prices(&self, _symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
+            Ok(self.prices.lock().unwrap().iter()
+                .filter(|point| point.timestamp >= *start_time && point.timestamp <= *end_time)
+                .cloned()
+                .collect())
+        }
+
+        fn cache_prices(&self, _symbol: &str, series: &PriceSeries) -> anyhow::Result<()> {
+            self.prices.lock().unwrap().extend(series.iter().cloned());
+            self.stored.lock().unwrap().extend(series.iter().cloned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_binance_provider_prices_smart_fetches_only_missing_windows() {
+        // 4 windows, the first and last are cached
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 4 - Duration::seconds(1);
+        let window_start = |n: i32| *START_TIME + BinancePriceProvider::TIME_WINDOW * n;
+        let cache = InMemoryPriceCache::default();
+        cache.prices.lock().unwrap().extend([
+            PricePoint { timestamp: window_start(0), price: dec!(10.0) },
+            PricePoint { timestamp: window_start(3), price: dec!(40.0) },
+        ]);
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL),
+                always(),
+                eq(Some(window_start(1).timestamp_millis())),
+                eq(Some(window_start(2).timestamp_millis() - 1)),
+                always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL),
+                always(),
+                eq(Some(window_start(2).timestamp_millis())),
+                eq(Some(window_start(3).timestamp_millis() - 1)),
+                always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_smart(SYMBOL, &START_TIME, &end_time, &cache).unwrap();
+
+        let timestamps: Vec<DateTime<Utc>> = prices.iter().map(|point| point.timestamp).collect();
+        assert_eq!( timestamps, (0..4).map(window_start).collect::<Vec<_>>() );
+        assert_eq!( prices[0].price, dec!(10.0) );
+        assert_eq!( prices[1].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[2].price, dec!(1.5) );
+        assert_eq!( prices[3].price, dec!(40.0) );
+
+        let stored: Vec<DateTime<Utc>> = cache.stored.lock().unwrap().iter().map(|point| point.timestamp).collect();
+        assert_eq!( stored, vec![window_start(1), window_start(2)] );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_smart_skips_api_when_fully_cached() {
+        let cache = InMemoryPriceCache::default();
+        cache.prices.lock().unwrap().push(PricePoint { timestamp: *START_TIME, price: dec!(10.0) });
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades().never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_smart(SYMBOL, &START_TIME, &END_TIME, &cache).unwrap();
+
+        assert_eq!( prices.len(), 1 );
+        assert_eq!( prices[0].price, dec!(10.0) );
+    }
+
+    #[test]
+    fn test_window_size_constructors_convert_to_duration() {
+        assert_eq!( WindowSize::seconds(30).unwrap().as_duration(), Duration::seconds(30) );
+        assert_eq!( WindowSize::minutes(5).unwrap().as_duration(), Duration::minutes(5) );
+        assert_eq!( Duration::from(WindowSize::hours(2).unwrap()), Duration::hours(2) );
+    }
+
+    #[test]
+    fn test_window_size_constructors_reject_zero_and_negative() {
+        assert!( WindowSize::seconds(0).is_err() );
+        assert!( WindowSize::minutes(-1).is_err() );
+        assert!( WindowSize::hours(0).is_err() );
+        assert!( WindowSize::hours(-3).is_err() );
+        assert!( WindowSize::milliseconds(0).is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_uses_configured_window_size() {
+        let window = Duration::minutes(5);
+        let end_time = *START_TIME + window * 2 - Duration::seconds(1);
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL),
+                always(),
+                eq(Some(START_TIME.timestamp_millis())),
+                eq(Some((*START_TIME + window).timestamp_millis() - 1)),
+                always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(
+                eq(SYMBOL),
+                always(),
+                eq(Some((*START_TIME + window).timestamp_millis())),
+                eq(Some(end_time.timestamp_millis())),
+                always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_window_size(WindowSize::minutes(5).unwrap());
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[1].timestamp, *START_TIME + window );
+    }
+
+    #[test]
+    fn test_binance_provider_latest_price_takes_most_recent_trade() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(None), eq(None), eq(None), eq(Some(1)))
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert_float_absolute_eq!( binance_provider.latest_price(SYMBOL).unwrap(), 0.01633102 );
+    }
+
+    struct FixedWatchlist(Vec<&'static str>);
+
+    impl Watchlist for FixedWatchlist {
+        fn tokens_or_defaults(&self, _defaults: &[&str]) -> anyhow::Result<Vec<String>> {
+            Ok(self.0.iter().map(|token| token.to_string()).collect())
+        }
+    }
+
+    #[test]
+    fn test_binance_provider_latest_for_watchlist_prices_each_token() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq("UNIUSDT"), eq(None), eq(None), eq(None), eq(Some(1)))
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq("ZRXUSDT"), eq(None), eq(None), eq(None), eq(Some(1)))
+            .returning(|_,_,_,_,_| Ok(
+                r#"[{"a": 26129,"p": "2.8","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.latest_for_watchlist(&FixedWatchlist(vec!["UNI", "ZRX"]), &[], 2).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_float_absolute_eq!( *prices["UNIUSDT"].as_ref().unwrap(), 0.01633102 );
+        assert_float_absolute_eq!( *prices["ZRXUSDT"].as_ref().unwrap(), 2.8 );
+    }
+
+    #[test]
+    fn test_binance_provider_latest_for_watchlist_keeps_prices_of_other_tokens_on_failure() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .with(eq("UNIUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .with(eq("ZRXUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Err(anyhow::anyhow!("connection reset")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.latest_for_watchlist(&FixedWatchlist(vec!["UNI", "ZRX"]), &[], 2).unwrap();
+
+        assert_float_absolute_eq!( *prices["UNIUSDT"].as_ref().unwrap(), 0.01633102 );
+        assert!( prices["ZRXUSDT"].is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_price_vs_average_computes_percentage_difference() {
+        let mut mock_api = MockBinanceAPI::new();
+        // latest trade
+        mock_api.expect_agg_trades()
+            .with(eq(SYMBOL), eq(None), eq(None), eq(None), eq(Some(1)))
+            .returning(|_,_,_,_,_| Ok(
+                r#"[{"a": 26129,"p": "2.8","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#.to_string()));
+        // historical windows
+        mock_api.expect_agg_trades()
+            .with(eq(SYMBOL), eq(None), function(|start: &Option<i64>| start.is_some()), always(), eq(None))
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let comparison = binance_provider.price_vs_average(SYMBOL, Duration::minutes(2)).unwrap();
+
+        assert_float_absolute_eq!( comparison.latest, 2.8 );
+        assert_float_absolute_eq!( comparison.average, 2.333333333 );
+        assert_float_absolute_eq!( comparison.pct_diff, 20.0, 1e-6 );
+    }
+
+    #[test]
+    fn test_binance_provider_price_vs_average_widens_windows_for_long_lookbacks() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .with(eq(SYMBOL), eq(None), eq(None), eq(None), eq(Some(1)))
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(10)
+            .with(eq(SYMBOL), eq(None), function(|start: &Option<i64>| start.is_some()), always(), eq(None))
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_max_windows(10);
+        let comparison = binance_provider.price_vs_average(SYMBOL, Duration::days(30)).unwrap();
+
+        assert_float_absolute_eq!( comparison.average, 0.01633102 );
+    }
+
+    #[test]
+    fn test_binance_provider_price_vs_average_errors_without_history() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        assert!( binance_provider.price_vs_average(SYMBOL, Duration::minutes(2)).is_err() );
+    }
+
+    /// Mock for 3 windows where the middle one has no trades
+    fn mock_api_with_empty_middle_window() -> MockBinanceAPI {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+        mock_api
+    }
+
+    #[test]
+    fn test_binance_provider_prices_with_fill_skip_leaves_gaps() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api_with_empty_middle_window()));
+        let prices = binance_provider.prices_with_fill(SYMBOL, &START_TIME, &end_time, FillPolicy::Skip).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_with_fill_forward_fills_empty_window() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api_with_empty_middle_window()));
+        let prices = binance_provider.prices_with_fill(SYMBOL, &START_TIME, &end_time, FillPolicy::ForwardFill).unwrap();
+
+        assert_eq!( prices.len(), 3 );
+        assert_eq!( prices[1].timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW );
+        assert_eq!( prices[1].price, dec!(7) / dec!(3) );
+        assert_eq!( prices[2].price, dec!(1.5) );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_with_fill_interpolates_empty_window() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api_with_empty_middle_window()));
+        let prices = binance_provider.prices_with_fill(SYMBOL, &START_TIME, &end_time, FillPolicy::Linear).unwrap();
+
+        assert_eq!( prices.len(), 3 );
+        assert_eq!( prices[1].timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW );
+        // 7/3 has no exact decimal form, compare at Binance's precision
+        assert_eq!( prices[1].price.round_dp(8), dec!(1.91666667) );
+    }
+
+    #[test]
+    fn test_avg_price_is_exact_where_f64_drifts() {
+        let response = concat!(
+            r#"[{"a": 1,"p": "0.1","q": "1.0","f": 1,"l": 1,"T": 1498793709153,"m": true,"M": true },"#,
+            r#"{"a": 2,"p": "0.2","q": "1.0","f": 2,"l": 2,"T": 1498793709153,"m": true,"M": true }]"#
+        );
+        assert_ne!( (0.1_f64 + 0.2) / 2.0, 0.15 );
+
+        assert_eq!( window_price(SYMBOL, response, SchemaMode::default(), Aggregation::Mean).unwrap(), Some(dec!(0.15)) );
+    }
+
+    #[test]
+    fn test_fill_gaps_leaves_out_leading_and_trailing_empty_windows() {
+        let window_start = |n: i32| *START_TIME + BinancePriceProvider::TIME_WINDOW * n;
+        let window_prices = vec![
+            (window_start(0), None),
+            (window_start(1), Some(dec!(1))),
+            (window_start(2), None),
+            (window_start(3), None),
+            (window_start(4), Some(dec!(4))),
+            (window_start(5), None),
+        ];
+
+        let forward: Vec<Decimal> = fill_gaps(window_prices.clone(), FillPolicy::ForwardFill).iter().map(|p| p.price).collect();
+        assert_eq!( forward, vec![dec!(1), dec!(1), dec!(1), dec!(4), dec!(4)] );
+
+        let linear = fill_gaps(window_prices, FillPolicy::Linear);
+        let linear_prices: Vec<Decimal> = linear.iter().map(|p| p.price).collect();
+        assert_eq!( linear_prices, vec![dec!(1), dec!(2), dec!(3), dec!(4)] );
+        assert_eq!( linear[0].timestamp, window_start(1) );
+    }
+
+    #[test]
+    fn test_binance_provider_range_vwap_weighs_trades_by_quantity() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(concat!(
+                r#"[{"a": 1,"p": "10.0","q": "1.0","f": 1,"l": 1,"T": 1498793709153,"m": true,"M": true },"#,
+                r#"{"a": 2,"p": "20.0","q": "3.0","f": 2,"l": 2,"T": 1498793709153,"m": true,"M": true }]"#
+            ).to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .returning(|_,_,_,_,_| Ok(
+                r#"[{"a": 3,"p": "30.0","q": "6.0","f": 3,"l": 3,"T": 1498793709153,"m": true,"M": true }]"#.to_string()));
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let vwap = binance_provider.range_vwap(SYMBOL, &START_TIME, &end_time).unwrap().unwrap();
+
+        // (10*1 + 20*3 + 30*6) / (1 + 3 + 6)
+        assert_float_absolute_eq!( vwap, 25.0 );
+    }
+
+    #[test]
+    fn test_binance_provider_range_vwap_pages_through_busy_windows() {
+        let trade = |id: i64, price: &str, time: i64| format!(
+            r#"{{"a": {},"p": "{}","q": "1.0","f": {},"l": {},"T": {},"m": true,"M": true }}"#, id, price, id, id, time);
+        let window_end = (*START_TIME + BinancePriceProvider::TIME_WINDOW).timestamp_millis() - 1;
+        let first_page: Vec<String> = (0..BinancePriceProvider::AGG_TRADES_PAGE_LIMIT)
+            .map(|id| trade(id, "10.0", START_TIME.timestamp_millis()))
+            .collect();
+        let first_page = format!("[{}]", first_page.join(","));
+        let next_page = format!("[{},{},{}]", trade(1000, "20.0", window_end), trade(1001, "20.0", window_end), trade(1002, "99.0", window_end + 1));
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(None), eq(Some(START_TIME.timestamp_millis())), eq(Some(window_end)), eq(Some(BinancePriceProvider::AGG_TRADES_PAGE_LIMIT)))
+            .return_once(move |_,_,_,_,_| Ok(first_page));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), eq(Some(1000)), eq(None), eq(None), eq(Some(BinancePriceProvider::AGG_TRADES_PAGE_LIMIT)))
+            .return_once(move |_,_,_,_,_| Ok(next_page));
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let vwap = binance_provider.range_vwap(SYMBOL, &START_TIME, &end_time).unwrap().unwrap();
+
+        // (10*1000 + 20*2) / 1002, the trade past the window is left out
+        assert_float_absolute_eq!( vwap, 10040.0 / 1002.0 );
+    }
+
+    #[test]
+    fn test_binance_provider_range_vwap_none_without_trades() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let vwap = binance_provider.range_vwap(SYMBOL, &START_TIME, &END_TIME).unwrap();
+
+        assert!( vwap.is_none() );
+    }
+
+    #[test]
+    fn test_binance_provider_daily_bucketing_queries_local_days() {
+        let tz = chrono_tz::America::New_York;
+        // Local midnights around the 2025-03-09 spring forward
+        let day_1 = Utc.with_ymd_and_hms(2025,3,8,5,0,0).unwrap();
+        let day_2 = Utc.with_ymd_and_hms(2025,3,9,5,0,0).unwrap();
+        let day_3 = Utc.with_ymd_and_hms(2025,3,10,4,0,0).unwrap();
+        let mut seq = mockall::Sequence::new();
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .in_sequence(&mut seq)
+            .with(eq(SYMBOL), always(), eq(Some(day_1.timestamp_millis())), eq(Some(day_2.timestamp_millis() - 1)), always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .in_sequence(&mut seq)
+            .with(eq(SYMBOL), always(), eq(Some(day_2.timestamp_millis())), eq(Some(day_3.timestamp_millis() - 1)), always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_bucketing(Bucketing::DailyTz(tz));
+        let prices = binance_provider.prices(SYMBOL, &day_1, &(day_3 - Duration::milliseconds(1))).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices[1].timestamp, day_2 );
+    }
+
+    fn kline(open_time: DateTime<Utc>, number_of_trades: i64) -> String {
+        let open_time = open_time.timestamp_millis();
+        format!(r#"[{},"1.0","1.0","1.0","1.0","1.0",{},"1.0",{},"1.0","1.0","0"]"#, open_time, open_time + 59_999, number_of_trades)
+    }
+
+    #[test]
+    fn test_binance_provider_count_active_windows_counts_windows_with_trades() {
+        let minute = |n: i32| *START_TIME + Duration::minutes(1) * n;
+        // Windows of 2 minutes: [0,1] has trades, [2,3] none, [4,5] trades on both klines
+        let klines = format!("[{},{},{},{},{},{}]",
+            kline(minute(0), 0), kline(minute(1), 7), kline(minute(2), 0),
+            kline(minute(3), 0), kline(minute(4), 3), kline(minute(5), 1));
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines()
+            .times(1)
+            .with(eq(SYMBOL), eq("1m"), eq(Some(START_TIME.timestamp_millis())), always(), always())
+            .returning(move |_,_,_,_,_| Ok(klines.clone()));
+        mock_api.expect_agg_trades().never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_window_size(WindowSize::minutes(2).unwrap());
+        let count = binance_provider.count_active_windows(SYMBOL, &START_TIME, &minute(6)).unwrap();
+
+        assert_eq!( count, 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_count_active_windows_probes_seconds_when_unaligned() {
+        let start_time = *START_TIME + Duration::seconds(30);
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_klines()
+            .times(1)
+            .with(eq(SYMBOL), eq("1s"), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let count = binance_provider.count_active_windows(SYMBOL, &start_time, &(start_time + Duration::minutes(3))).unwrap();
+
+        assert_eq!( count, 0 );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_for_symbols_keys_series_by_symbol() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq("ETHUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq("BTCUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_for_symbols(&["ETHUSDT", "BTCUSDT"], &START_TIME, &END_TIME).unwrap();
+
+        assert_eq!( prices.len(), 2 );
+        assert_eq!( prices["ETHUSDT"][0].price, dec!(0.01633102) );
+        assert_eq!( prices["BTCUSDT"][0].price, dec!(1.5) );
+    }
+
+    #[test]
+    fn test_binance_provider_ratio_series_aligns_shared_windows() {
+        let trade = |price: &str| format!(r#"[{{"a": 1,"p": "{}","q": "1.0","f": 1,"l": 1,"T": 1498793709153,"m": true,"M": true }}]"#, price);
+        let start_time = Utc.with_ymd_and_hms(2025, 1, 27, 14, 0, 0).unwrap();
+        let end_time = Utc.with_ymd_and_hms(2025, 1, 27, 14, 3, 59).unwrap();
+        let minute = move |start: Option<i64>| (start.unwrap() - start_time.timestamp_millis()) / 60_000;
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .with(eq("ETHUSDT"), always(), always(), always(), always())
+            .returning(move |_,_,start,_,_| Ok(trade(["3.0", "4.0", "5.0", "6.0"][minute(start) as usize])));
+        // Minute 1 has no trades and minute 2 a zero price, both are skipped
+        mock_api.expect_agg_trades()
+            .with(eq("BTCUSDT"), always(), always(), always(), always())
+            .returning(move |_,_,start,_,_| Ok(match minute(start) {
+                0 => trade("1.5"),
+                1 => "[]".to_string(),
+                2 => trade("0.0"),
+                _ => trade("4.0"),
+            }));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let ratio = binance_provider.ratio_series("ETHUSDT", "BTCUSDT", &start_time, &end_time).unwrap();
 
-    pub fn new(binance_api: Box<dyn BinanceAPI>) -> BinancePriceProvider {
-        BinancePriceProvider { binance_api }
+        assert_eq!( ratio.len(), 2 );
+        assert_eq!( ratio[0].timestamp, start_time );
+        assert_eq!( ratio[0].price, dec!(2) );
+        assert_eq!( ratio[1].timestamp, start_time + Duration::minutes(3) );
+        assert_eq!( ratio[1].price, dec!(1.5) );
     }
 
-    fn fetch_avg_price_for_window(&self, symbol: &str, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>) -> anyhow::Result<Option<f64>> {
-        let api_response = self.binance_api.agg_trades(
-            symbol,
-            None,
-            Some( window_start.timestamp_millis() ),
-            Some( window_end.timestamp_millis() ),
-            None)?;
-        let response_json: AggTradesResponse = serde_json::from_str(&api_response)?;
+    #[test]
+    fn test_binance_provider_prices_for_symbols_parallel_keeps_per_symbol_results() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .with(eq("ETHUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .with(eq("BTCUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+        mock_api.expect_agg_trades()
+            .with(eq("BNBUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(INVALID_PRICE_RESPONSE.to_string()));
 
-        let response_prices: Vec<f64> = response_json
-            .iter()
-            .map(|trade| trade.p.parse::<f64>())
-            .collect::<Result<Vec<f64>, _>>()?;
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let prices = binance_provider.prices_for_symbols_parallel(&["ETHUSDT", "BTCUSDT", "BNBUSDT"], &START_TIME, &END_TIME, 2);
 
-        let sum = response_prices.iter().sum::<f64>();
-        let count = response_prices.len() as f64;
-        
-        if !response_prices.is_empty() { Ok(Some(sum / count)) } else { Ok(None) }
+        assert_eq!( prices.len(), 3 );
+        assert_eq!( prices["ETHUSDT"].as_ref().unwrap()[0].price, dec!(0.01633102) );
+        assert_eq!( prices["BTCUSDT"].as_ref().unwrap()[0].price, dec!(1.5) );
+        assert!( matches!(prices["BNBUSDT"], Err(PriceError::Decode(_))) );
     }
 
-    pub fn prices(&self, symbol: &str, start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> anyhow::Result<PriceSeries> {
-        let mut prices = Vec::new();
-        let window_starts = std::iter::successors(Some(*start_time), |prev| {
-            let next = *prev + Self::TIME_WINDOW;
-            if next < *end_time { Some(next) } else { None }
-        });
-        for window_start in window_starts {
-            let window_end = std::cmp::min(
-                window_start + Self::TIME_WINDOW - Duration::milliseconds(1),
-                *end_time);
-            let maybe_price = self.fetch_avg_price_for_window(symbol, &window_start, &window_end)?;
-            if let Some(avg_price) = maybe_price {
-                prices.push(PricePoint { timestamp: window_start, price: avg_price });
-            }
-        }
-        Ok(prices)
+    #[test]
+    fn test_binance_provider_prices_for_symbols_fails_fast() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq("ETHUSDT"), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(INVALID_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .with(eq("BTCUSDT"), always(), always(), always(), always())
+            .never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let error = binance_provider.prices_for_symbols(&["ETHUSDT", "BTCUSDT"], &START_TIME, &END_TIME).unwrap_err();
+
+        assert!( error.to_string().contains("ETHUSDT") );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    use mockall::mock;
-    use mockall::predicate::*;
-    extern crate assert_float_eq;
-    use assert_float_eq::assert_float_absolute_eq;
-    use chrono::prelude::*;
-    use std::sync::LazyLock;
+    #[test]
+    fn test_binance_provider_retries_failed_windows_within_budget() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let mut mock_api = MockBinanceAPI::new();
+        // Every other request fails
+        mock_api.expect_agg_trades()
+            .times(6)
+            .returning(move |_,_,_,_,_| match calls.fetch_add(1, Ordering::Relaxed) % 2 {
+                0 => Err(anyhow::anyhow!("connection reset")),
+                _ => Ok(SINGLE_PRICE_RESPONSE.to_string()),
+            });
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
 
-    // TODO: since creating a test api with mockito is lightweight this tests need improvement
-    // Use a mocked API here and integrate the BinanceApi component into the whole test suite
-    // This will improve test quality and allow further TDD'ing
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_retry_budget(3);
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
 
-    mock! {
-        BinanceAPI {}
-        impl BinanceAPI for BinanceAPI {
-            fn agg_trades(&self, 
-                          symbol: &str,
-                          from_id: Option<i64>,
-                          start_time: Option<i64>,
-                          end_time: Option<i64>,
-                          limit: Option<i64>) -> anyhow::Result<String>;
-        }
+        assert_eq!( prices.len(), 3 );
     }
 
-    const SYMBOL: &'static str = "BTCUSDC";
+    #[test]
+    fn test_binance_provider_waits_before_retrying_a_window() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(2)
+            .returning(move |_,_,_,_,_| match calls.fetch_add(1, Ordering::Relaxed) {
+                0 => Err(anyhow::anyhow!("connection reset")),
+                _ => Ok(SINGLE_PRICE_RESPONSE.to_string()),
+            });
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW;
 
-    const SINGLE_PRICE_RESPONSE: &'static str = r#"[{"a": 26129,"p": "0.01633102","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
-    const MULTIPLE_PRICES_RESPONSE: &'static str = concat!(
-        r#"[{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
-        r#"{"a": 26129,"p": "2.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
-        r#"{"a": 26129,"p": "3.5","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#
-    );
-    const MULTIPLE_PRICES_RESPONSE_2: &'static str = concat!(
-        r#"[{"a": 26129,"p": "1.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true },"#,
-        r#"{"a": 26129,"p": "2.0","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#,
-    );
-    const MISSING_PRICE_RESPONSE: &'static str = r#"[{"a": 26129,"q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
-    const INVALID_PRICE_RESPONSE: &'static str = r#"[{"a": 26129,"p": "notafloat","q": "4.70443515","f": 27781,"l": 27781,"T": 1498793709153,"m": true,"M": true }]"#;
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_retry_budget(1)
+            .with_retry_backoff(Arc::new(crate::retry::FixedBackoff(std::time::Duration::from_millis(50))));
+        let started = std::time::Instant::now();
+        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
 
-    // Default time values spaning just one time window
-    const START_TIME: LazyLock<DateTime<Utc>> = LazyLock::new( || 
-        Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap() );
-    const END_TIME: LazyLock<DateTime<Utc>> = LazyLock::new( || 
-        *START_TIME + BinancePriceProvider::TIME_WINDOW - Duration::seconds(1) );    
+        assert_eq!( prices.len(), 1 );
+        assert!( started.elapsed() >= std::time::Duration::from_millis(50) );
+    }
 
     #[test]
-    fn test_can_create_a_binance_price_provider() {
-        let mock_api = MockBinanceAPI::new();
-        let _binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+    fn test_binance_provider_aborts_once_retry_budget_is_exhausted() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let mut mock_api = MockBinanceAPI::new();
+        // Every other request fails, the third failure exceeds the budget of 2
+        mock_api.expect_agg_trades()
+            .times(5)
+            .returning(move |_,_,_,_,_| match calls.fetch_add(1, Ordering::Relaxed) % 2 {
+                0 => Err(anyhow::anyhow!("connection reset")),
+                _ => Ok(SINGLE_PRICE_RESPONSE.to_string()),
+            });
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 10;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_retry_budget(2);
+        let result = binance_provider.prices(SYMBOL, &START_TIME, &end_time);
+
+        assert!( matches!(result, Err(PriceError::Http(_))) );
     }
 
     #[test]
-    fn test_binance_provider_returns_empty_when_no_prices() {
+    fn test_binance_provider_does_not_retry_by_default() {
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
             .times(1)
-            .with(
-                eq(SYMBOL), 
-                always(), 
-                always(), 
-                always(), 
-                always())
-            .returning(|_,_,_,_,_| Ok("[]".to_string()));
+            .returning(|_,_,_,_,_| Err(anyhow::anyhow!("connection reset")));
 
         let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME);
 
-        assert!( prices.is_ok() );
-        assert!( prices.unwrap().is_empty() );
+        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
     }
 
     #[test]
-    fn test_binance_provider_returns_price_if_just_one_price() {
+    fn test_binance_provider_price_stats_from_single_window() {
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
             .times(1)
-            .with(
-                eq(SYMBOL), 
-                always(), 
-                always(), 
-                always(), 
-                always())
-            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
 
         let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
-        
-        assert_eq!( prices.len(), 1 );
-        assert_float_absolute_eq!( prices[0].price, 0.01633102 );
+        let stats = binance_provider.price_stats(SYMBOL, &START_TIME, &END_TIME).unwrap();
+
+        assert_eq!( stats.len(), 1 );
+        assert_eq!( stats[0].timestamp, *START_TIME );
+        assert_float_absolute_eq!( stats[0].min, 1.0 );
+        assert_float_absolute_eq!( stats[0].max, 3.5 );
+        assert_eq!( stats[0].count, 3 );
+        assert_float_absolute_eq!( stats[0].avg, 2.333333333 );
     }
 
     #[test]
-    fn test_binance_provider_returns_error_on_api_error() {
+    fn test_binance_provider_price_stats_skips_empty_windows() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api_with_empty_middle_window()));
+        let stats = binance_provider.price_stats(SYMBOL, &START_TIME, &end_time).unwrap();
+
+        assert_eq!( stats.len(), 2 );
+        assert_eq!( stats[1].timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW * 2 );
+        assert_eq!( stats[1].count, 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_latest_from_ticker() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_ticker_24hr()
+            .times(1)
+            .with(eq(SYMBOL))
+            .returning(|_| Ok(r#"{"lastPrice": "4.00000200", "priceChangePercent": "-95.960",
+                "highPrice": "100.00000000", "lowPrice": "0.10000000", "volume": "8913.30000000"}"#.to_string()));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+
+        assert_float_absolute_eq!( binance_provider.latest(SYMBOL).unwrap(), 4.000002 );
+    }
+
+    #[derive(Default)]
+    struct RecordingPriceStore {
+        batches: Mutex<Vec<(String, PriceSeries)>>,
+    }
+
+    impl PriceStore for RecordingPriceStore {
+        fn store_prices(&self, symbol: &str, series: &PriceSeries) -> anyhow::Result<()> {
+            self.batches.lock().unwrap().push((symbol.to_string(), series.clone()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_binance_provider_fetch_and_store_writes_fetched_windows_in_one_batch() {
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+        let store = RecordingPriceStore::default();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api_with_empty_middle_window()));
+        let prices = binance_provider.fetch_and_store(SYMBOL, &START_TIME, &end_time, &store).unwrap();
+
+        let batches = store.batches.lock().unwrap();
+        assert_eq!( batches.len(), 1 );
+        assert_eq!( batches[0].0, SYMBOL );
+        assert_eq!( batches[0].1.len(), 2 );
+        assert_eq!( prices.len(), 2 );
+    }
+
+    #[test]
+    fn test_binance_provider_fetch_and_store_stores_nothing_on_failure() {
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
             .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
-        
+        let store = RecordingPriceStore::default();
+
         let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
+
+        assert!( binance_provider.fetch_and_store(SYMBOL, &START_TIME, &END_TIME, &store).is_err() );
+        assert!( store.batches.lock().unwrap().is_empty() );
     }
 
     #[test]
-    fn test_binance_provider_returns_error_on_missing_price_data() {
+    fn test_binance_provider_fetches_allowed_symbol() {
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
-            .returning(|_,_,_,_,_| Ok(MISSING_PRICE_RESPONSE.to_string()));
+            .times(1)
+            .with(eq(SYMBOL), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
 
-        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_allowed_symbols(&[SYMBOL, "ETHUSDT"]);
+
+        assert_eq!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap().len(), 1 );
     }
 
     #[test]
-    fn test_binance_provider_returns_error_on_non_numeric_price_data() {
+    fn test_binance_provider_rejects_symbols_not_allowed_without_fetching() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades().never();
+        mock_api.expect_exchange_info().never();
+        mock_api.expect_ticker_24hr().never();
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_allowed_symbols(&[SYMBOL, "ETHUSDT"])
+            .with_blocked_symbols(&["ETHUSDT"])
+            .with_validate_symbols(true);
+
+        let result = binance_provider.prices("ETHUSDT", &START_TIME, &END_TIME);
+        assert!( matches!(result, Err(PriceError::SymbolNotAllowed(symbol)) if symbol == "ETHUSDT") );
+        let result = binance_provider.prices_parallel("DOGEUSDT", &START_TIME, &END_TIME, 2);
+        assert!( matches!(result, Err(PriceError::SymbolNotAllowed(_))) );
+        assert!( binance_provider.latest("DOGEUSDT").is_err() );
+    }
+
+    #[test]
+    fn test_binance_provider_normalizes_symbol_in_every_variant() {
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
-            .returning(|_,_,_,_,_| Ok(INVALID_PRICE_RESPONSE.to_string()));
+            .times(3)
+            .with(eq(SYMBOL), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_klines()
+            .times(1)
+            .with(eq(SYMBOL), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
 
-        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        assert!( binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).is_err() );
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_allowed_symbols(&[SYMBOL]);
+
+        assert_eq!( binance_provider.prices_parallel("btcusdc", &START_TIME, &END_TIME, 2).unwrap().len(), 1 );
+        assert_eq!( binance_provider.price_stats("btcUSDC", &START_TIME, &END_TIME).unwrap().len(), 1 );
+        assert!( binance_provider.latest_price("btcusdc").is_ok() );
+        assert_eq!( binance_provider.count_active_windows("btcusdc", &START_TIME, &END_TIME).unwrap(), 0 );
     }
 
     #[test]
-    fn test_binance_provider_returns_average_price_from_single_time_window() {
+    fn test_binance_provider_checks_symbol_in_every_variant() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_exchange_info()
+            .returning(|| Ok(EXCHANGE_INFO_RESPONSE.to_string()));
+        mock_api.expect_agg_trades().never();
+        mock_api.expect_klines().never();
+
+        // DOGEUSDT is allowed but not listed
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api))
+            .with_allowed_symbols(&[SYMBOL, "DOGEUSDT"])
+            .with_validate_symbols(true);
+        let unknown = |err: anyhow::Error| matches!(err.downcast_ref::<PriceError>(), Some(PriceError::UnknownSymbol(_)));
+
+        assert!( unknown(binance_provider.price_stats("DOGEUSDT", &START_TIME, &END_TIME).unwrap_err()) );
+        assert!( unknown(binance_provider.prices_with_counts("DOGEUSDT", &START_TIME, &END_TIME).unwrap_err()) );
+        assert!( unknown(binance_provider.prices_smart("DOGEUSDT", &START_TIME, &END_TIME, &InMemoryPriceCache::default()).unwrap_err()) );
+        assert!( unknown(binance_provider.range_vwap("DOGEUSDT", &START_TIME, &END_TIME).unwrap_err()) );
+        assert!( unknown(binance_provider.count_active_windows("DOGEUSDT", &START_TIME, &END_TIME).unwrap_err()) );
+        assert!( unknown(binance_provider.latest_price("DOGEUSDT").unwrap_err()) );
+        assert!( matches!(binance_provider.prices_parallel("DOGEUSDT", &START_TIME, &END_TIME, 2), Err(PriceError::UnknownSymbol(_))) );
+        assert!( matches!(binance_provider.prices_with_fill("DOGEUSDT", &START_TIME, &END_TIME, FillPolicy::Skip), Err(PriceError::UnknownSymbol(_))) );
+        assert!( matches!(binance_provider.prices_by_id("DOGEUSDT", 1, 10), Err(PriceError::UnknownSymbol(_))) );
+        // ETHUSDT is listed but not allowed
+        let result = binance_provider.range_vwap("ETHUSDT", &START_TIME, &END_TIME).unwrap_err();
+        assert!( matches!(result.downcast_ref::<PriceError>(), Some(PriceError::SymbolNotAllowed(_))) );
+    }
+
+    #[test]
+    fn test_binance_provider_price_stats_average_trade_size() {
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
             .times(1)
-            .with(
-                eq(SYMBOL), 
-                always(), 
-                eq(Some(START_TIME.timestamp_millis())), 
-                eq(Some(END_TIME.timestamp_millis())), 
-                always())
-            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
+            .returning(|_,_,_,_,_| Ok(concat!(
+                r#"[{"a": 1,"p": "10.0","q": "1.5","f": 1,"l": 1,"T": 1498793709153,"m": true,"M": true },"#,
+                r#"{"a": 2,"p": "20.0","q": "3.0","f": 2,"l": 2,"T": 1498793709153,"m": true,"M": true },"#,
+                r#"{"a": 3,"p": "30.0","q": "6.0","f": 3,"l": 3,"T": 1498793709153,"m": true,"M": true }]"#
+            ).to_string()));
 
         let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        let prices = binance_provider.prices(SYMBOL, &START_TIME, &END_TIME).unwrap();
+        let stats = binance_provider.price_stats(SYMBOL, &START_TIME, &END_TIME).unwrap();
 
-        assert_eq!( prices.len(), 1 );
-        assert_float_absolute_eq!( prices[0].price, 2.333333333 );
-        assert_eq!( prices[0].timestamp, *START_TIME );
+        // total volume 10.5 over 3 trades
+        assert_float_absolute_eq!( stats[0].avg_trade_size, 10.5 / 3.0 );
+    }
+
+    #[derive(Default)]
+    struct InMemoryFetchProgress {
+        last_fetched: Mutex<HashMap<String, DateTime<Utc>>>,
+    }
+
+    impl FetchProgress for InMemoryFetchProgress {
+        fn get_last_fetched(&self, symbol: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
+            Ok(self.last_fetched.lock().unwrap().get(symbol).copied())
+        }
+
+        fn set_last_fetched(&self, symbol: &str, timestamp: &DateTime<Utc>) -> anyhow::Result<()> {
+            self.last_fetched.lock().unwrap().insert(symbol.to_string(), *timestamp);
+            Ok(())
+        }
     }
 
     #[test]
-    fn test_binance_provider_returns_average_prices_from_multiple_time_windows() {
-        // ensure to capture just 2 windows
-        let first_window_end = *START_TIME + BinancePriceProvider::TIME_WINDOW;
-        let end_time = first_window_end + Duration::seconds(1);
+    fn test_binance_provider_prices_incremental_starts_from_last_fetched() {
+        let progress = InMemoryFetchProgress::default();
+        progress.set_last_fetched(SYMBOL, &START_TIME).unwrap();
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 2;
 
         let mut mock_api = MockBinanceAPI::new();
-        // call #1
         mock_api.expect_agg_trades()
             .times(1)
-            .with(
-                eq(SYMBOL), 
-                always(), 
-                eq(Some(START_TIME.timestamp_millis())), 
-                eq(Some(first_window_end.timestamp_millis()-1)), 
-                always() )
-            .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
-        // call #2
+            .with(eq(SYMBOL), always(), eq(Some(START_TIME.timestamp_millis())), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
         mock_api.expect_agg_trades()
-        .times(1)
-        .with(
-            eq(SYMBOL), 
-            always(), 
-            eq(Some(first_window_end.timestamp_millis())), 
-            eq(Some(end_time.timestamp_millis())), 
-            always() )
-        .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+            .times(1)
+            .with(eq(SYMBOL), always(), always(), eq(Some(end_time.timestamp_millis() - 1)), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
 
         let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
-        
+        let prices = binance_provider.prices_incremental(SYMBOL, &progress, &end_time).unwrap();
+
         assert_eq!( prices.len(), 2 );
-        assert_float_absolute_eq!( prices[0].price, 2.333333333 );
-        assert_eq!( prices[0].timestamp, *START_TIME );
-        assert_float_absolute_eq!( prices[1].price, 1.5 );
-        assert_eq!( prices[1].timestamp, first_window_end );
+        assert_eq!( progress.get_last_fetched(SYMBOL).unwrap(), Some(end_time) );
     }
 
     #[test]
-    fn test_binance_provider_skips_time_windows_with_no_prices() {
-       // capture 3 windows
-       let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 3;
+    fn test_binance_provider_prices_incremental_defaults_to_lookback() {
+        let progress = InMemoryFetchProgress::default();
+        let end_time = *START_TIME + BinancePriceProvider::DEFAULT_INCREMENTAL_LOOKBACK;
 
-       let mut mock_api = MockBinanceAPI::new();
-       // call #1
-       mock_api.expect_agg_trades()
-           .times(1)
-           .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE.to_string()));
-        // call #2
+        let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
-        .times(1)
-        .returning(|_,_,_,_,_| Ok("[]".to_string()));
-        // call #3
+            .times(1)
+            .with(eq(SYMBOL), always(), eq(Some(START_TIME.timestamp_millis())), always(), always())
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
         mock_api.expect_agg_trades()
-           .times(1)
-           .returning(|_,_,_,_,_| Ok(MULTIPLE_PRICES_RESPONSE_2.to_string()));
+            .returning(|_,_,_,_,_| Ok("[]".to_string()));
 
         let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        let prices = binance_provider.prices(SYMBOL, &START_TIME, &end_time).unwrap();
-        
-        assert_eq!( prices.len(), 2 );
-        assert_float_absolute_eq!( prices[0].price, 2.333333333 );
-        assert_eq!( prices[0].timestamp, *START_TIME );
-        assert_float_absolute_eq!( prices[1].price, 1.5 );
-        assert_eq!( prices[1].timestamp, *START_TIME + BinancePriceProvider::TIME_WINDOW * 2 );
+        binance_provider.prices_incremental(SYMBOL, &progress, &end_time).unwrap();
+
+        assert_eq!( progress.get_last_fetched(SYMBOL).unwrap(), Some(end_time) );
     }
 
     #[test]
-    fn test_binance_provider_returns_prices_for_given_symbol() {
-        const NEW_SYMBOL: &'static str = "ETHUSDT";
+    fn test_binance_provider_prices_incremental_catches_up_in_chunks_of_max_windows() {
+        let progress = InMemoryFetchProgress::default();
+        progress.set_last_fetched(SYMBOL, &START_TIME).unwrap();
+        let chunk_end = |n: i32| *START_TIME + BinancePriceProvider::TIME_WINDOW * (10 * n);
+        let end_time = chunk_end(2) + BinancePriceProvider::TIME_WINDOW * 5;
 
         let mut mock_api = MockBinanceAPI::new();
         mock_api.expect_agg_trades()
-            .times(1)
-            .with(
-                eq(NEW_SYMBOL),
-                always(),
-                always(),
-                always(),
-                always(),
-            )
+            .times(25)
             .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
 
-        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
-        let _ = binance_provider.prices(NEW_SYMBOL, &START_TIME, &END_TIME);
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_max_windows(10);
+        let prices = binance_provider.prices_incremental(SYMBOL, &progress, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 25 );
+        assert!( prices.windows(2).all(|pair| pair[1].timestamp - pair[0].timestamp == BinancePriceProvider::TIME_WINDOW) );
+        assert_eq!( progress.get_last_fetched(SYMBOL).unwrap(), Some(end_time) );
+    }
+
+    #[test]
+    fn test_binance_provider_prices_incremental_keeps_progress_of_completed_chunks() {
+        let progress = InMemoryFetchProgress::default();
+        progress.set_last_fetched(SYMBOL, &START_TIME).unwrap();
+        let second_chunk = *START_TIME + BinancePriceProvider::TIME_WINDOW * 10;
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW * 30;
+
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .with(eq(SYMBOL), always(), function(move |start: &Option<i64>| start.unwrap() < second_chunk.timestamp_millis()), always(), always())
+            .returning(|_,_,_,_,_| Ok(SINGLE_PRICE_RESPONSE.to_string()));
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api)).with_max_windows(10).with_retry_budget(0);
+        let prices = binance_provider.prices_incremental(SYMBOL, &progress, &end_time).unwrap();
+
+        assert_eq!( prices.len(), 10 );
+        assert_eq!( progress.get_last_fetched(SYMBOL).unwrap(), Some(second_chunk) );
     }
 
+    #[test]
+    fn test_binance_provider_prices_incremental_keeps_marker_on_failure() {
+        let progress = InMemoryFetchProgress::default();
+        progress.set_last_fetched(SYMBOL, &START_TIME).unwrap();
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("some error")));
+
+        let binance_provider = BinancePriceProvider::new(Box::new(mock_api));
+        let end_time = *START_TIME + BinancePriceProvider::TIME_WINDOW;
+
+        assert!( binance_provider.prices_incremental(SYMBOL, &progress, &end_time).is_err() );
+        assert_eq!( progress.get_last_fetched(SYMBOL).unwrap(), Some(*START_TIME) );
+    }
 }
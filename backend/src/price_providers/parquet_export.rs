@@ -0,0 +1,74 @@
+use super::{to_f64, PriceSeries};
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, TimestampMillisecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Points per row group, only one group's columns are held in memory at a time.
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+fn schema() -> Arc<Schema> {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        Field::new("price", DataType::Float64, false),
+    ]))
+}
+
+/// Writes the series to a Parquet file at `path`, replacing it if it exists.
+///
+/// Columns are `timestamp` (milliseconds, UTC) and `price` (`Float64`, so prices are
+/// rounded to the nearest double). Points are written in row groups of `ROW_GROUP_SIZE`.
+pub fn write_parquet(series: &PriceSeries, path: &Path) -> anyhow::Result<()> {
+    let schema = schema();
+    let properties = WriterProperties::builder().set_max_row_group_size(ROW_GROUP_SIZE).build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))?;
+    for chunk in series.chunks(ROW_GROUP_SIZE) {
+        let timestamps = TimestampMillisecondArray::from_iter_values(chunk.iter().map(|point| point.timestamp.timestamp_millis()))
+            .with_timezone("UTC");
+        let prices = chunk.iter().map(|point| to_f64(point.price)).collect::<Result<Vec<_>, _>>()?;
+        let prices = Float64Array::from(prices);
+        let columns: Vec<ArrayRef> = vec![Arc::new(timestamps), Arc::new(prices)];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_providers::PricePoint;
+    use arrow_array::Array;
+    use chrono::{Duration, TimeZone, Utc};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_write_parquet_round_trip() {
+        let start = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        let series: PriceSeries = [dec!(101000.5), dec!(0.01633102), dec!(2)].iter().enumerate()
+            .map(|(i, price)| PricePoint { timestamp: start + Duration::minutes(i as i64), price: *price })
+            .collect();
+        let path = std::env::temp_dir().join(format!("prices-{}.parquet", uuid::Uuid::new_v4()));
+
+        write_parquet(&series, &path).unwrap();
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap()
+            .build().unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.schema().field(0).data_type(), &DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())));
+        let timestamps = batch.column(0).as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
+        let prices = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(timestamps.len(), 3);
+        for (i, point) in series.iter().enumerate() {
+            assert_eq!(timestamps.value(i), point.timestamp.timestamp_millis());
+            assert_eq!(prices.value(i), to_f64(point.price).unwrap());
+        }
+    }
+}
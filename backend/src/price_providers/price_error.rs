@@ -0,0 +1,69 @@
+use super::binance_price_provider::binance_api::BinanceApiError;
+use chrono::{DateTime, Utc};
+
+/// Binance error code for a symbol that isn't listed.
+pub(crate) const INVALID_SYMBOL_CODE: i64 = -1121;
+
+/// Errors produced while fetching prices, so callers can tell failures apart.
+#[derive(Debug, thiserror::Error)]
+pub enum PriceError {
+    /// The request couldn't be completed (network down, 5xx, non rate-limit 4xx...).
+    #[error("HTTP request failed: {0:#}")]
+    Http(anyhow::Error),
+    /// The response body isn't the expected JSON.
+    #[error("Failed to decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// A trade price that isn't a number.
+    #[error("Invalid price {0:?}")]
+    InvalidPrice(String),
+    /// A trade time, in millis, outside of the range `DateTime` can represent.
+    #[error("Invalid trade time {0}")]
+    InvalidTradeTime(i64),
+    /// The symbol isn't in Binance's format, nothing was fetched.
+    #[error("Invalid symbol {0:?}")]
+    InvalidSymbol(String),
+    /// The symbol isn't listed on the exchange info, see `is_unknown_symbol`.
+    #[error("Unknown symbol {0}")]
+    UnknownSymbol(String),
+    /// Binance answered with its error envelope, e.g. `{"code":-1121,"msg":"Invalid symbol."}`.
+    #[error("Binance error {code} for {symbol}: {msg}")]
+    Binance { symbol: String, code: i64, msg: String },
+    /// The symbol is excluded by the provider's allow or block list, nothing was fetched.
+    #[error("Symbol {0} is not allowed")]
+    SymbolNotAllowed(String),
+    /// The range is empty or reversed, no window can be formed.
+    #[error("Invalid range: start {start} must be before end {end}")]
+    InvalidRange { start: DateTime<Utc>, end: DateTime<Utc> },
+    /// The range would need more requests than the provider allows.
+    #[error("Range spans {count} windows, more than the maximum of {max}")]
+    TooManyWindows { count: i64, max: i64 },
+    /// Binance answered 429, the request weight budget is exhausted.
+    #[error("Rate limited by Binance")]
+    RateLimited,
+}
+
+impl PriceError {
+    /// Whether the symbol doesn't exist, from validation or Binance's invalid symbol error.
+    pub fn is_unknown_symbol(&self) -> bool {
+        matches!(self, PriceError::UnknownSymbol(_) | PriceError::Binance { code: INVALID_SYMBOL_CODE, .. })
+    }
+
+    /// Fails unless `start_time < end_time`.
+    pub fn check_range(start_time: &DateTime<Utc>, end_time: &DateTime<Utc>) -> Result<(), PriceError> {
+        if start_time >= end_time {
+            return Err(PriceError::InvalidRange { start: *start_time, end: *end_time });
+        }
+        Ok(())
+    }
+
+    /// Classifies an error returned by the API client for a request about `symbol`.
+    pub fn from_api_error(symbol: &str, err: anyhow::Error) -> Self {
+        if let Some(api_error) = err.chain().find_map(|cause| cause.downcast_ref::<BinanceApiError>()) {
+            return PriceError::Binance { symbol: symbol.to_string(), code: api_error.code, msg: api_error.msg.clone() };
+        }
+        let rate_limited = err.chain()
+            .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .any(|cause| cause.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        if rate_limited { PriceError::RateLimited } else { PriceError::Http(err) }
+    }
+}
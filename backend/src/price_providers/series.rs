@@ -0,0 +1,554 @@
+use super::{mean, PricePoint, PriceSeries};
+use anyhow::{bail, Context};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// Number of preceding points used as reference by `flag_outliers`.
+const OUTLIER_ROLLING_WINDOW: usize = 20;
+
+/// How the points falling in one `resample` bucket, or the trades of a window
+/// (see `BinancePriceProvider::with_aggregation`), become a single price.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    /// Earliest point of the bucket.
+    First,
+    /// Latest point of the bucket.
+    Last,
+    Min,
+    Max,
+}
+
+/// Mean and population standard deviation of the given values.
+fn mean_and_std_dev(values: &[f64]) -> (f64, f64) {
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+    (mean, variance.sqrt())
+}
+
+/// Returns the indices of suspected bad ticks.
+///
+/// Each point is compared against the mean and standard deviation of up to
+/// `OUTLIER_ROLLING_WINDOW` preceding points (excluding itself, so a spike can't hide
+/// by inflating its own reference). A point is flagged when it lies more than
+/// `z_threshold` standard deviations away from that rolling mean. The first two
+/// points have too little history and are never flagged. If the reference is flat
+/// (zero deviation) any different price is flagged.
+pub fn flag_outliers(series: &PriceSeries, z_threshold: f64) -> Vec<usize> {
+    let prices: Vec<f64> = series.iter().map(|point| point.price.to_f64().unwrap_or(f64::NAN)).collect();
+    (2..prices.len())
+        .filter(|&i| {
+            let reference = &prices[i.saturating_sub(OUTLIER_ROLLING_WINDOW)..i];
+            let (mean, std_dev) = mean_and_std_dev(reference);
+            let deviation = (prices[i] - mean).abs();
+            if std_dev == 0.0 { deviation > 0.0 } else { deviation / std_dev > z_threshold }
+        })
+        .collect()
+}
+
+/// Summary of the prices of a series, see `stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeriesStats {
+    pub count: usize,
+    pub mean: f64,
+    /// Population standard deviation.
+    pub std_dev: f64,
+    pub min: Decimal,
+    pub max: Decimal,
+    pub first: Decimal,
+    pub last: Decimal,
+}
+
+/// Count, mean, standard deviation and extremes of the series prices, `None` when empty.
+///
+/// Mean and variance are accumulated with Welford's method, so long series don't
+/// lose precision to a huge sum of squares.
+pub fn stats(series: &PriceSeries) -> Option<SeriesStats> {
+    let (first, last) = (series.first()?.price, series.last()?.price);
+    let (mut mean, mut sum_sq_diff) = (0.0, 0.0);
+    let (mut min, mut max) = (first, first);
+    for (i, point) in series.iter().enumerate() {
+        let price = point.price.to_f64().unwrap_or(f64::NAN);
+        let delta = price - mean;
+        mean += delta / (i + 1) as f64;
+        sum_sq_diff += delta * (price - mean);
+        min = min.min(point.price);
+        max = max.max(point.price);
+    }
+    Some(SeriesStats {
+        count: series.len(),
+        mean,
+        std_dev: (sum_sq_diff / series.len() as f64).sqrt(),
+        min,
+        max,
+        first,
+        last,
+    })
+}
+
+/// Simple period-over-period returns `(p[i] - p[i-1]) / p[i-1]`, one per consecutive pair.
+///
+/// The output always has `series.len() - 1` values, the i-th one ending at point i + 1.
+/// A return from a previous price of zero isn't defined and is `None`.
+pub fn returns(series: &PriceSeries) -> Vec<Option<f64>> {
+    exact_returns(series).map(|r| r.and_then(|r| r.to_f64())).collect()
+}
+
+/// `returns` without leaving decimal arithmetic.
+fn exact_returns(series: &PriceSeries) -> impl Iterator<Item = Option<Decimal>> + '_ {
+    series.windows(2)
+        .map(|pair| {
+            let (previous, current) = (pair[0].price, pair[1].price);
+            (!previous.is_zero()).then(|| (current - previous) / previous)
+        })
+}
+
+/// Change from the first to the last point, `(last - first) / first`.
+///
+/// `None` for fewer than two points or when the first price is zero.
+pub fn total_change(series: &PriceSeries) -> Option<f64> {
+    let (first, last) = (series.first()?.price, series.get(1..)?.last()?.price);
+    if first.is_zero() {
+        return None;
+    }
+    ((last - first) / first).to_f64()
+}
+
+/// Growth of `base` invested at the first point: `base * prod(1 + r_i)` at each point.
+///
+/// The first point is always `base`. Once a -100% return takes the value to zero it stays there.
+pub fn cumulative_returns(series: &PriceSeries, base: Decimal) -> PriceSeries {
+    let growth = exact_returns(series).scan(base, |value, r| {
+        // Past a zero price the value is already zero, or the series started at zero
+        *value *= Decimal::ONE + r.unwrap_or(Decimal::ZERO);
+        Some(*value)
+    });
+    series.iter()
+        .zip(std::iter::once(base).chain(growth))
+        .map(|(point, price)| PricePoint { timestamp: point.timestamp, price })
+        .collect()
+}
+
+/// Sharpe-like quality score: mean of `returns` over their standard deviation, not annualized.
+///
+/// Uses the population standard deviation over the defined returns. `None` with fewer
+/// than two of them or when all returns are equal (zero variance).
+pub fn return_sharpe(series: &PriceSeries) -> Option<f64> {
+    let returns: Vec<f64> = returns(series).into_iter().flatten().collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let (mean, std_dev) = mean_and_std_dev(&returns);
+    if std_dev == 0.0 { None } else { Some(mean / std_dev) }
+}
+
+/// Downsamples an ascending series to one point per `interval`, aggregating each bucket by `agg`.
+///
+/// Buckets are aligned to the epoch and timestamped at their start, buckets without
+/// points are skipped. Fails when `interval` isn't positive or is smaller than the
+/// shortest spacing between consecutive points, as that would be upsampling.
+pub fn resample(series: &PriceSeries, interval: Duration, agg: Aggregation) -> anyhow::Result<PriceSeries> {
+    let interval_millis = interval.num_milliseconds();
+    if interval_millis <= 0 {
+        bail!("Resampling interval must be positive, got {}", interval);
+    }
+    if let Some(spacing) = series.windows(2).map(|pair| pair[1].timestamp - pair[0].timestamp).filter(|gap| *gap > Duration::zero()).min() {
+        if interval < spacing {
+            bail!("Resampling interval {} is smaller than the series spacing {}", interval, spacing);
+        }
+    }
+
+    let mut resampled = Vec::new();
+    for bucket in series.chunk_by(|a, b| bucket_start(a, interval_millis) == bucket_start(b, interval_millis)) {
+        let millis = bucket_start(&bucket[0], interval_millis);
+        let timestamp = DateTime::from_timestamp_millis(millis)
+            .with_context(|| format!("Invalid bucket start {}", millis))?;
+        let prices: Vec<Decimal> = bucket.iter().map(|point| point.price).collect();
+        let price = match agg {
+            Aggregation::Mean => mean(&prices),
+            Aggregation::First => prices.first().copied(),
+            Aggregation::Last => prices.last().copied(),
+            Aggregation::Min => prices.iter().min().copied(),
+            Aggregation::Max => prices.iter().max().copied(),
+        };
+        if let Some(price) = price {
+            resampled.push(PricePoint { timestamp, price });
+        }
+    }
+    Ok(resampled)
+}
+
+/// Reduces the series to `max_points` points with largest-triangle-three-buckets,
+/// which keeps the points that shape the plotted line (peaks, troughs).
+///
+/// The first and last points are always kept, series with at most `max_points`
+/// are returned unchanged. Fails when `max_points < 2`.
+pub fn downsample(series: &PriceSeries, max_points: usize) -> anyhow::Result<PriceSeries> {
+    if max_points < 2 {
+        bail!("Downsampling needs at least 2 points, got {}", max_points);
+    }
+    if series.len() <= max_points {
+        return Ok(series.clone());
+    }
+    let xy = |point: &PricePoint| (point.timestamp.timestamp_millis() as f64, point.price.to_f64().unwrap_or(f64::NAN));
+
+    // The points between first and last are split into `max_points - 2` buckets, one point kept per bucket
+    let bucket_size = (series.len() - 2) as f64 / (max_points - 2) as f64;
+    let bucket = |i: usize| {
+        let start = (i as f64 * bucket_size) as usize + 1;
+        let end = (((i + 1) as f64 * bucket_size) as usize + 1).min(series.len() - 1);
+        &series[start..end]
+    };
+
+    let mut sampled = Vec::with_capacity(max_points);
+    sampled.push(series[0].clone());
+    let mut previous = xy(&series[0]);
+    for i in 0..max_points - 2 {
+        // Third vertex of the triangles: the average of the next bucket, or the last point
+        let next = if i + 1 < max_points - 2 { bucket(i + 1) } else { &series[series.len() - 1..] };
+        let (next_x, next_y) = next.iter().map(xy).fold((0.0, 0.0), |(x, y), (px, py)| (x + px, y + py));
+        let (next_x, next_y) = (next_x / next.len() as f64, next_y / next.len() as f64);
+
+        let area = |point: &PricePoint| {
+            let (x, y) = xy(point);
+            ((previous.0 - next_x) * (y - previous.1) - (previous.0 - x) * (next_y - previous.1)).abs()
+        };
+        let chosen = bucket(i).iter()
+            .max_by(|a, b| area(a).total_cmp(&area(b)))
+            .expect("buckets are never empty");
+        previous = xy(chosen);
+        sampled.push(chosen.clone());
+    }
+    sampled.push(series[series.len() - 1].clone());
+    Ok(sampled)
+}
+
+/// Invariant broken by a series, see `validate_series`.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum SeriesError {
+    #[error("Duplicate timestamp {timestamp} at index {index}")]
+    DuplicateTimestamp { index: usize, timestamp: DateTime<Utc> },
+    #[error("Timestamp {timestamp} at index {index} is before the previous one {previous}")]
+    OutOfOrder { index: usize, previous: DateTime<Utc>, timestamp: DateTime<Utc> },
+}
+
+/// Checks that timestamps are strictly increasing, reporting the first offending point.
+///
+/// Prices need no check, a `Decimal` can't be NaN or infinite.
+pub fn validate_series(series: &PriceSeries) -> Result<(), SeriesError> {
+    for (i, pair) in series.windows(2).enumerate() {
+        let (previous, timestamp) = (pair[0].timestamp, pair[1].timestamp);
+        if timestamp == previous {
+            return Err(SeriesError::DuplicateTimestamp { index: i + 1, timestamp });
+        }
+        if timestamp < previous {
+            return Err(SeriesError::OutOfOrder { index: i + 1, previous, timestamp });
+        }
+    }
+    Ok(())
+}
+
+/// Merges two series into one strictly ascending series without duplicate timestamps,
+/// e.g. cached prices `a` with freshly fetched prices `b`. On conflicts the point of `b`
+/// wins.
+///
+/// Both inputs must be sorted by ascending timestamp, as returned by `prices`, which
+/// keeps the merge O(n + m).
+pub fn merge_series(a: PriceSeries, b: PriceSeries) -> PriceSeries {
+    let mut merged: PriceSeries = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.into_iter().peekable(), b.into_iter().peekable());
+    loop {
+        let (point, fresh) = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x.timestamp < y.timestamp => (a.next().unwrap(), false),
+            (Some(x), Some(y)) => {
+                if x.timestamp == y.timestamp {
+                    a.next();
+                }
+                (b.next().unwrap(), true)
+            }
+            (Some(_), None) => (a.next().unwrap(), false),
+            (None, Some(_)) => (b.next().unwrap(), true),
+            (None, None) => break,
+        };
+        match merged.last_mut() {
+            Some(last) if last.timestamp == point.timestamp => if fresh { *last = point },
+            _ => merged.push(point),
+        }
+    }
+    merged
+}
+
+/// Epoch millis of the start of the `interval_millis` bucket holding `point`.
+fn bucket_start(point: &PricePoint, interval_millis: i64) -> i64 {
+    point.timestamp.timestamp_millis().div_euclid(interval_millis) * interval_millis
+}
+
+/// Writes the series as CSV: a `timestamp,price` header then one row per point,
+/// timestamps in RFC 3339 UTC with milliseconds and prices exactly as stored.
+/// An empty series gives just the header.
+pub fn write_csv<W: std::io::Write>(series: &PriceSeries, w: &mut W) -> std::io::Result<()> {
+    writeln!(w, "timestamp,price")?;
+    for point in series {
+        writeln!(w, "{},{}", point.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true), point.price)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_float_eq::assert_float_absolute_eq;
+    use chrono::prelude::*;
+    use chrono::Duration;
+
+    /// Builds a series with one point per minute
+    fn series_from(prices: &[f64]) -> PriceSeries {
+        let start = Utc.with_ymd_and_hms(2025,1,27,14,0,0).unwrap();
+        prices.iter().enumerate()
+            .map(|(i, price)| PricePoint { timestamp: start + Duration::minutes(i as i64), price: Decimal::try_from(*price).unwrap() })
+            .collect()
+    }
+
+    #[test]
+    fn test_flag_outliers_flags_injected_spike() {
+        let series = series_from(&[100.0, 101.0, 99.5, 100.5, 100.0, 150.0, 100.2, 99.8, 100.1]);
+        assert_eq!( flag_outliers(&series, 3.0), vec![5] );
+    }
+
+    #[test]
+    fn test_flag_outliers_returns_empty_for_stable_series() {
+        let series = series_from(&[100.0, 101.0, 99.5, 100.5, 100.0, 100.7, 100.2, 99.8, 100.1]);
+        assert!( flag_outliers(&series, 3.0).is_empty() );
+    }
+
+    #[test]
+    fn test_flag_outliers_returns_empty_for_short_series() {
+        assert!( flag_outliers(&series_from(&[]), 3.0).is_empty() );
+        assert!( flag_outliers(&series_from(&[100.0, 500.0]), 3.0).is_empty() );
+    }
+
+    #[test]
+    fn test_cumulative_returns_compounds_returns_from_base() {
+        // returns: +10%, -10%, +50%
+        let series = series_from(&[100.0, 110.0, 99.0, 148.5]);
+        let cumulative = cumulative_returns(&series, Decimal::ONE);
+
+        let values: Vec<String> = cumulative.iter().map(|point| point.price.normalize().to_string()).collect();
+        assert_eq!( values, vec!["1", "1.1", "0.99", "1.485"] );
+        for (point, original) in cumulative.iter().zip(series.iter()) {
+            assert_eq!( point.timestamp, original.timestamp );
+        }
+    }
+
+    #[test]
+    fn test_cumulative_returns_stays_at_zero_after_total_loss() {
+        let series = series_from(&[100.0, 0.0, 50.0, 80.0]);
+        let cumulative = cumulative_returns(&series, Decimal::ONE_HUNDRED);
+
+        let values: Vec<Decimal> = cumulative.iter().map(|point| point.price).collect();
+        assert_eq!( values, vec![Decimal::ONE_HUNDRED, Decimal::ZERO, Decimal::ZERO, Decimal::ZERO] );
+    }
+
+    #[test]
+    fn test_cumulative_returns_of_empty_series_is_empty() {
+        assert!( cumulative_returns(&series_from(&[]), Decimal::ONE).is_empty() );
+    }
+
+    #[test]
+    fn test_write_csv_writes_header_and_rows() {
+        let mut series = series_from(&[101000.5, 0.0]);
+        series[1].price = Decimal::from_str_exact("0.01633102").unwrap();
+        let mut csv = Vec::new();
+
+        write_csv(&series, &mut csv).unwrap();
+
+        assert_eq!( String::from_utf8(csv).unwrap(), concat!(
+            "timestamp,price\n",
+            "2025-01-27T14:00:00.000Z,101000.5\n",
+            "2025-01-27T14:01:00.000Z,0.01633102\n",
+        ) );
+    }
+
+    #[test]
+    fn test_write_csv_of_empty_series_is_just_the_header() {
+        let mut csv = Vec::new();
+        write_csv(&series_from(&[]), &mut csv).unwrap();
+        assert_eq!( String::from_utf8(csv).unwrap(), "timestamp,price\n" );
+    }
+
+    #[test]
+    fn test_returns_and_total_change_of_known_prices() {
+        let series = series_from(&[100.0, 110.0, 99.0]);
+
+        let returns = returns(&series);
+        assert_eq!( returns.len(), 2 );
+        assert_float_absolute_eq!( returns[0].unwrap(), 0.10 );
+        assert_float_absolute_eq!( returns[1].unwrap(), -0.10 );
+        assert_float_absolute_eq!( total_change(&series).unwrap(), -0.01 );
+    }
+
+    #[test]
+    fn test_returns_and_total_change_from_zero_or_short_series() {
+        assert_eq!( returns(&series_from(&[0.0, 10.0, 20.0])), vec![None, Some(1.0)] );
+        assert!( returns(&series_from(&[100.0])).is_empty() );
+        assert!( total_change(&series_from(&[0.0, 10.0])).is_none() );
+        assert!( total_change(&series_from(&[100.0])).is_none() );
+        assert!( total_change(&series_from(&[])).is_none() );
+    }
+
+    #[test]
+    fn test_return_sharpe_of_known_returns() {
+        // returns: +10%, -10%, +50%
+        let series = series_from(&[100.0, 110.0, 99.0, 148.5]);
+        assert_float_absolute_eq!( return_sharpe(&series).unwrap(), 0.668153104 );
+    }
+
+    #[test]
+    fn test_return_sharpe_is_none_without_enough_data_or_variance() {
+        assert!( return_sharpe(&series_from(&[])).is_none() );
+        assert!( return_sharpe(&series_from(&[100.0, 110.0])).is_none() );
+        // constant +10% returns
+        assert!( return_sharpe(&series_from(&[100.0, 110.0, 121.0])).is_none() );
+    }
+
+    #[test]
+    fn test_resample_minutes_into_two_minute_means() {
+        let series = series_from(&[1.0, 3.0, 5.0, 7.0, 9.0, 11.0]);
+
+        let resampled = resample(&series, Duration::minutes(2), Aggregation::Mean).unwrap();
+
+        assert_eq!( resampled.len(), 3 );
+        assert_eq!( resampled[0].timestamp, series[0].timestamp );
+        assert_eq!( resampled[1].timestamp, series[2].timestamp );
+        assert_eq!( resampled[2].timestamp, series[4].timestamp );
+        assert_eq!( resampled.iter().map(|point| point.price).collect::<Vec<_>>(), vec![Decimal::from(2), Decimal::from(6), Decimal::from(10)] );
+    }
+
+    #[test]
+    fn test_resample_aggregations_and_upsampling() {
+        let series = series_from(&[4.0, 1.0, 3.0]);
+        let price = |agg| resample(&series, Duration::hours(1), agg).unwrap()[0].price;
+
+        assert_eq!( price(Aggregation::First), Decimal::from(4) );
+        assert_eq!( price(Aggregation::Last), Decimal::from(3) );
+        assert_eq!( price(Aggregation::Min), Decimal::from(1) );
+        assert_eq!( price(Aggregation::Max), Decimal::from(4) );
+        assert!( resample(&series, Duration::seconds(30), Aggregation::Mean).is_err() );
+    }
+
+    #[test]
+    fn test_stats_of_known_series() {
+        let stats = stats(&series_from(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0])).unwrap();
+
+        assert_eq!( stats.count, 8 );
+        assert_float_absolute_eq!( stats.mean, 5.0 );
+        assert_float_absolute_eq!( stats.std_dev, 2.0 );
+        assert_eq!( stats.min, Decimal::from(2) );
+        assert_eq!( stats.max, Decimal::from(9) );
+        assert_eq!( stats.first, Decimal::from(2) );
+        assert_eq!( stats.last, Decimal::from(9) );
+    }
+
+    #[test]
+    fn test_stats_of_single_point_and_empty_series() {
+        let stats_single = stats(&series_from(&[101000.5])).unwrap();
+
+        assert_eq!( stats_single.count, 1 );
+        assert_float_absolute_eq!( stats_single.mean, 101000.5 );
+        assert_float_absolute_eq!( stats_single.std_dev, 0.0 );
+        assert_eq!( stats_single.min, stats_single.max );
+        assert!( stats(&series_from(&[])).is_none() );
+    }
+
+    #[test]
+    fn test_downsample_keeps_max_points_and_ends() {
+        let prices: Vec<f64> = (0..100).map(|i| 100.0 + (i as f64 / 5.0).sin() * 10.0).collect();
+        let series = series_from(&prices);
+
+        let sampled = downsample(&series, 10).unwrap();
+
+        assert_eq!( sampled.len(), 10 );
+        assert_eq!( sampled.first(), series.first() );
+        assert_eq!( sampled.last(), series.last() );
+        assert!( sampled.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp) );
+    }
+
+    #[test]
+    fn test_downsample_keeps_spikes() {
+        let mut prices = vec![1.0; 50];
+        prices[23] = 50.0;
+        let series = series_from(&prices);
+
+        let sampled = downsample(&series, 5).unwrap();
+
+        assert!( sampled.contains(&series[23]) );
+    }
+
+    #[test]
+    fn test_downsample_short_series_and_invalid_target() {
+        let series = series_from(&[1.0, 2.0, 3.0]);
+
+        assert_eq!( downsample(&series, 3).unwrap(), series );
+        assert!( downsample(&series, 1).is_err() );
+    }
+
+    #[test]
+    fn test_merge_series_of_disjoint_series_interleaves() {
+        let all = series_from(&[1.0, 2.0, 3.0, 4.0]);
+        let a = vec![all[0].clone(), all[2].clone()];
+        let b = vec![all[1].clone(), all[3].clone()];
+
+        assert_eq!( merge_series(a.clone(), b.clone()), all );
+        assert_eq!( merge_series(b, a), all );
+    }
+
+    #[test]
+    fn test_merge_series_fully_overlapping_prefers_fresh() {
+        let cached = series_from(&[1.0, 2.0, 3.0]);
+        let fresh = series_from(&[10.0, 20.0, 30.0]);
+
+        assert_eq!( merge_series(cached, fresh.clone()), fresh );
+    }
+
+    #[test]
+    fn test_merge_series_partially_overlapping_dedups() {
+        let cached = series_from(&[1.0, 2.0, 3.0]);
+        let fresh: PriceSeries = series_from(&[0.0, 20.0, 30.0, 40.0]).into_iter().skip(1).collect();
+
+        let merged = merge_series(cached.clone(), fresh.clone());
+
+        assert_eq!( merged.len(), 4 );
+        assert_eq!( merged[0], cached[0] );
+        assert_eq!( merged[1..], fresh[..] );
+        assert!( merged.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp) );
+        assert!( merge_series(Vec::new(), Vec::new()).is_empty() );
+    }
+
+    #[test]
+    fn test_validate_series_accepts_increasing_and_empty_series() {
+        assert_eq!( validate_series(&series_from(&[1.0, 2.0, 3.0])), Ok(()) );
+        assert_eq!( validate_series(&Vec::new()), Ok(()) );
+    }
+
+    #[test]
+    fn test_validate_series_reports_duplicate_timestamp() {
+        let mut series = series_from(&[1.0, 2.0, 3.0]);
+        series[2].timestamp = series[1].timestamp;
+
+        assert_eq!( validate_series(&series), Err(SeriesError::DuplicateTimestamp { index: 2, timestamp: series[1].timestamp }) );
+    }
+
+    #[test]
+    fn test_validate_series_reports_out_of_order_timestamp() {
+        let mut series = series_from(&[1.0, 2.0, 3.0]);
+        series.swap(0, 1);
+
+        assert_eq!(
+            validate_series(&series),
+            Err(SeriesError::OutOfOrder { index: 1, previous: series[0].timestamp, timestamp: series[1].timestamp })
+        );
+    }
+}
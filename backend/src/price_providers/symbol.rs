@@ -0,0 +1,73 @@
+use super::PriceError;
+use std::fmt;
+
+/// Shortest and longest symbol Binance lists, e.g. `BTCUSDC`.
+const MIN_SYMBOL_LEN: usize = 2;
+const MAX_SYMBOL_LEN: usize = 20;
+
+/// A Binance symbol, uppercase ASCII letters and digits only.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Symbol(String);
+
+impl Symbol {
+    /// Validates `symbol`, lowercase letters are uppercased.
+    pub fn new(symbol: &str) -> Result<Self, PriceError> {
+        let normalized = symbol.to_ascii_uppercase();
+        let valid_len = (MIN_SYMBOL_LEN..=MAX_SYMBOL_LEN).contains(&normalized.len());
+        if !valid_len || !normalized.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+            return Err(PriceError::InvalidSymbol(symbol.to_string()));
+        }
+        Ok(Symbol(normalized))
+    }
+
+    /// The symbol as sent in query params.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for Symbol {
+    type Error = PriceError;
+
+    fn try_from(symbol: &str) -> Result<Self, PriceError> {
+        Symbol::new(symbol)
+    }
+}
+
+impl TryFrom<&Symbol> for Symbol {
+    type Error = PriceError;
+
+    fn try_from(symbol: &Symbol) -> Result<Self, PriceError> {
+        Ok(symbol.clone())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_symbol() {
+        assert_eq!( Symbol::new("BTCUSDC").unwrap().as_str(), "BTCUSDC" );
+        assert_eq!( Symbol::new("1INCHUSDT").unwrap().as_str(), "1INCHUSDT" );
+    }
+
+    #[test]
+    fn test_lowercase_symbol_is_normalized() {
+        assert_eq!( Symbol::new("btcUsdc").unwrap(), Symbol::new("BTCUSDC").unwrap() );
+    }
+
+    #[test]
+    fn test_invalid_symbols_are_rejected() {
+        assert!( matches!(Symbol::new("BTC-USDC"), Err(PriceError::InvalidSymbol(symbol)) if symbol == "BTC-USDC") );
+        assert!( Symbol::new("B").is_err() );
+        assert!( Symbol::new("").is_err() );
+        assert!( Symbol::new(&"A".repeat(MAX_SYMBOL_LEN + 1)).is_err() );
+    }
+}
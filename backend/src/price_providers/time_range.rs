@@ -0,0 +1,74 @@
+use super::PriceError;
+use chrono::{DateTime, Duration, Utc};
+
+/// A `[start, end]` range of prices, see `aligned_to` to get deterministic window bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimeRange {
+    /// Fails unless `start < end`.
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Self, PriceError> {
+        PriceError::check_range(&start, &end)?;
+        Ok(TimeRange { start, end })
+    }
+
+    /// The smallest range covering this one whose bounds are multiples of `window` since
+    /// the epoch: `start` is snapped down and `end` up. Windows of the result then start at
+    /// the same instants whatever the requested range, so cached and fresh windows match.
+    ///
+    /// A `window` that isn't positive leaves the range as is.
+    pub fn aligned_to(&self, window: Duration) -> TimeRange {
+        let window = window.num_milliseconds();
+        if window <= 0 {
+            return *self;
+        }
+        let snap = |millis: i64| DateTime::from_timestamp_millis(millis).expect("aligned bound out of range");
+        let start = self.start.timestamp_millis().div_euclid(window) * window;
+        let end = self.end.timestamp_millis();
+        let end = end.div_euclid(window) * window + if end.rem_euclid(window) == 0 { 0 } else { window };
+        TimeRange { start: snap(start), end: snap(end) }
+    }
+
+    /// Number of `window`-long windows needed to cover the range, the last one may be partial.
+    pub fn window_count(&self, window: Duration) -> i64 {
+        let (span, window) = ((self.end - self.start).num_milliseconds(), window.num_milliseconds().max(1));
+        (span + window - 1) / window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 27, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_aligned_to_snaps_start_down_and_end_up() {
+        let range = TimeRange::new(at(14, 0, 37), at(14, 2, 10)).unwrap();
+
+        let aligned = range.aligned_to(Duration::minutes(1));
+
+        assert_eq!( aligned, TimeRange { start: at(14, 0, 0), end: at(14, 3, 0) } );
+        assert_eq!( aligned.window_count(Duration::minutes(1)), 3 );
+    }
+
+    #[test]
+    fn test_aligned_range_stays_aligned() {
+        let range = TimeRange::new(at(14, 0, 0), at(15, 0, 0)).unwrap();
+
+        assert_eq!( range.aligned_to(Duration::minutes(1)), range );
+        assert_eq!( range.aligned_to(Duration::minutes(1)).window_count(Duration::minutes(1)), 60 );
+        assert_eq!( range.window_count(Duration::minutes(7)), 9 );
+    }
+
+    #[test]
+    fn test_new_rejects_reversed_range() {
+        assert!( matches!(TimeRange::new(at(15, 0, 0), at(14, 0, 0)), Err(PriceError::InvalidRange { .. })) );
+    }
+}
@@ -0,0 +1,214 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait before retry number `attempt` (starting at 1).
+pub trait BackoffStrategy: std::fmt::Debug {
+    fn delay(&self, attempt: u32) -> Duration;
+}
+
+/// Waits the same delay before every retry.
+#[derive(Clone, Debug)]
+pub struct FixedBackoff(pub Duration);
+
+impl BackoffStrategy for FixedBackoff {
+    fn delay(&self, _attempt: u32) -> Duration {
+        self.0
+    }
+}
+
+/// Waits `step * attempt`.
+#[derive(Clone, Debug)]
+pub struct LinearBackoff {
+    pub step: Duration,
+}
+
+impl BackoffStrategy for LinearBackoff {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.step * attempt
+    }
+}
+
+/// Waits `base * 2^(attempt-1)` plus, when `jitter` is set, a random extra of up to `base`.
+#[derive(Clone, Debug)]
+pub struct ExponentialBackoff {
+    pub base: Duration,
+    pub jitter: bool,
+}
+
+impl BackoffStrategy for ExponentialBackoff {
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.base * 2u32.saturating_pow(attempt.saturating_sub(1));
+        if self.jitter {
+            backoff + self.base.mul_f64(rand::thread_rng().gen::<f64>())
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Decorrelated jitter: a random delay between `base` and `base * 3^(attempt-1)`, capped at `cap`.
+/// Spreads retries from many clients better than plain exponential backoff.
+#[derive(Clone, Debug)]
+pub struct DecorrelatedJitterBackoff {
+    pub base: Duration,
+    pub cap: Duration,
+}
+
+impl BackoffStrategy for DecorrelatedJitterBackoff {
+    fn delay(&self, attempt: u32) -> Duration {
+        let upper = (self.base * 3u32.saturating_pow(attempt.saturating_sub(1))).min(self.cap);
+        let lower = self.base.min(upper);
+        lower + (upper - lower).mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Full jitter: a random delay between 0 and `base * 2^(attempt-1)`, the latter capped at `max`.
+/// Retries of many clients spread over the whole window instead of bunching at its end.
+#[derive(Debug)]
+pub struct FullJitterBackoff {
+    base: Duration,
+    max: Duration,
+    rng: Mutex<StdRng>,
+}
+
+impl FullJitterBackoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self { base, max, rng: Mutex::new(StdRng::from_entropy()) }
+    }
+
+    /// Draws the same delays on every run, e.g. for tests.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+}
+
+impl BackoffStrategy for FullJitterBackoff {
+    fn delay(&self, attempt: u32) -> Duration {
+        let upper = (self.base * 2u32.saturating_pow(attempt.saturating_sub(1))).min(self.max);
+        upper.mul_f64(self.rng.lock().unwrap().gen::<f64>())
+    }
+}
+
+/// How many times and how patiently `with_backoff` retries, e.g. `BinanceHttpClient`
+/// on transient failures (connection errors, 5xx and 429 responses).
+///
+/// Defaults to 3 retries with exponential backoff from 200ms plus jitter.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff: Arc<dyn BackoffStrategy + Send + Sync>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Arc::new(ExponentialBackoff { base: Duration::from_millis(200), jitter: true }),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `attempt` (starting at 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.delay(attempt)
+    }
+}
+
+/// Runs `op` until it succeeds, retrying errors for which `is_retryable` holds up to
+/// `policy.max_retries` times, waiting `policy.delay` before each retry.
+/// The last error is returned once retries run out, a non-retryable one right away.
+pub fn with_backoff<T, E>(
+    policy: &RetryPolicy,
+    op: impl FnMut() -> Result<T, E>,
+    is_retryable: impl Fn(&E) -> bool,
+) -> Result<T, E> {
+    retry_with(policy, op, |err, attempt| is_retryable(err).then(|| policy.delay(attempt)))
+}
+
+/// Same as `with_backoff` with `next_delay` deciding both whether the error of attempt
+/// number `attempt` is retried and how long to wait first, e.g. as a server asked to.
+pub fn retry_with<T, E>(
+    policy: &RetryPolicy,
+    mut op: impl FnMut() -> Result<T, E>,
+    mut next_delay: impl FnMut(&E, u32) -> Option<Duration>,
+) -> Result<T, E> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let err = match op() {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        if attempt > policy.max_retries {
+            return Err(err);
+        }
+        match next_delay(&err, attempt) {
+            Some(delay) => std::thread::sleep(delay),
+            None => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Retries at most `max_retries` times with seeded full jitter of at most 4ms
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            backoff: Arc::new(FullJitterBackoff::new(Duration::from_millis(1), Duration::from_millis(4)).with_seed(42)),
+        }
+    }
+
+    /// Op failing its first `failures` calls, counting calls in `attempts`
+    fn failing(failures: u32, attempts: &mut u32) -> impl FnMut() -> Result<&'static str, &'static str> + '_ {
+        move || {
+            *attempts += 1;
+            if *attempts <= failures { Err("transient") } else { Ok("done") }
+        }
+    }
+
+    #[test]
+    fn test_with_backoff_retries_until_success() {
+        let mut attempts = 0;
+        let result = with_backoff(&policy(3), failing(2, &mut attempts), |_| true);
+
+        assert_eq!(result, Ok("done"));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_with_backoff_gives_up_after_max_retries() {
+        let mut attempts = 0;
+        let result = with_backoff(&policy(3), failing(10, &mut attempts), |_| true);
+
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn test_with_backoff_returns_non_retryable_errors_right_away() {
+        let mut attempts = 0;
+        let result = with_backoff(&policy(3), failing(10, &mut attempts), |err| *err != "transient");
+
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_full_jitter_backoff_is_bounded_and_reproducible_with_seed() {
+        let backoff = || FullJitterBackoff::new(Duration::from_millis(100), Duration::from_millis(300)).with_seed(7);
+        let (first, second) = (backoff(), backoff());
+
+        for attempt in 1..=5 {
+            let delay = first.delay(attempt);
+            assert_eq!(delay, second.delay(attempt));
+            assert!(delay <= Duration::from_millis(100 * 2u64.pow(attempt - 1)).min(Duration::from_millis(300)));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::metrics::MetricsExporter;
+use crate::price_providers::{to_f64, BinancePriceProvider, PriceError, PriceSeries};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Query string of `GET /prices`, fields are optional so missing ones get a clear 400.
+#[derive(Deserialize)]
+pub struct PricesQuery {
+    symbol: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PricePointResponse {
+    timestamp: String,
+    price: f64,
+}
+
+fn to_response(series: PriceSeries) -> Result<Vec<PricePointResponse>, PriceError> {
+    series.into_iter()
+        .map(|point| Ok(PricePointResponse {
+            timestamp: point.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            price: to_f64(point.price)?,
+        }))
+        .collect()
+}
+
+/// Errors a request can fail with, rendered as `{"error": "..."}`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// Missing or unparseable parameters.
+    BadRequest(String),
+    /// Binance couldn't be queried.
+    BadGateway(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::BadGateway(message) => (StatusCode::BAD_GATEWAY, message),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, ApiError> {
+    value.as_deref().ok_or_else(|| ApiError::BadRequest(format!("Missing parameter {}", name)))
+}
+
+fn parse_time(value: &str, name: &str) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|err| ApiError::BadRequest(format!("Invalid {} {:?}: {}", name, value, err)))
+}
+
+async fn get_prices(State(provider): State<Arc<BinancePriceProvider>>, Query(query): Query<PricesQuery>) -> Result<Json<Vec<PricePointResponse>>, ApiError> {
+    let symbol = required(&query.symbol, "symbol")?.to_string();
+    let start_time = parse_time(required(&query.start, "start")?, "start")?;
+    let end_time = parse_time(required(&query.end, "end")?, "end")?;
+    if start_time >= end_time {
+        return Err(ApiError::BadRequest("start must be before end".to_string()));
+    }
+
+    // The provider makes blocking HTTP calls, keep them off the async workers
+    let prices = tokio::task::spawn_blocking(move || provider.prices(symbol.as_str(), &start_time, &end_time))
+        .await
+        .map_err(|err| ApiError::BadGateway(err.to_string()))?
+        .map_err(|err| match err {
+            PriceError::InvalidSymbol(_) | PriceError::SymbolNotAllowed(_) | PriceError::InvalidRange { .. } | PriceError::TooManyWindows { .. } => ApiError::BadRequest(err.to_string()),
+            err if err.is_unknown_symbol() => ApiError::BadRequest(err.to_string()),
+            err => ApiError::BadGateway(err.to_string()),
+        })?;
+    let prices = to_response(prices).map_err(|err| ApiError::BadGateway(err.to_string()))?;
+    Ok(Json(prices))
+}
+
+/// Routes of the REST API:
+///
+/// * `GET /prices?symbol=BTCUSDC&start=<RFC 3339>&end=<RFC 3339>` - `prices` as a JSON array
+///   of `{"timestamp", "price"}`.
+pub fn router(provider: Arc<BinancePriceProvider>) -> Router {
+    Router::new()
+        .route("/prices", get(get_prices))
+        .with_state(provider)
+}
+
+async fn get_metrics(State(exporter): State<Arc<MetricsExporter>>) -> Result<impl IntoResponse, ApiError> {
+    // Rendering PINGs Redis, which blocks
+    let body = tokio::task::spawn_blocking(move || exporter.render())
+        .await
+        .map_err(|err| ApiError::BadGateway(err.to_string()))?
+        .map_err(|err| ApiError::BadGateway(err.to_string()))?;
+    Ok(([(axum::http::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body))
+}
+
+/// `GET /metrics` - the metrics of `exporter` for Prometheus to scrape.
+pub fn metrics_router(exporter: Arc<MetricsExporter>) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(exporter)
+}
+
+/// Serves `app`, e.g. `router` merged with `metrics_router`, on `addr` until the process is stopped.
+pub async fn serve(addr: SocketAddr, app: Router) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Listening on {}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_db::LocalDb;
+    use crate::price_providers::binance_price_provider::binance_api::{BinanceAPI, BinanceHttpClient};
+    use crate::price_providers::binance_price_provider::mock_binance_api::MockBinanceAPI;
+    use mockall::predicate::*;
+
+    const SYMBOL: &str = "BTCUSDC";
+    const START: &str = "2025-01-27T14:00:00Z";
+    const END: &str = "2025-01-27T14:00:59Z";
+
+    /// Starts the server on a free port, returns its base url
+    async fn spawn_server(mock_api: MockBinanceAPI) -> String {
+        let provider = Arc::new(BinancePriceProvider::new(Box::new(mock_api)));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(provider)).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    async fn get(url: &str) -> (StatusCode, serde_json::Value) {
+        let resp = reqwest::get(url).await.unwrap();
+        let status = StatusCode::from_u16(resp.status().as_u16()).unwrap();
+        (status, serde_json::from_str(&resp.text().await.unwrap()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_get_prices_returns_series_as_json() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .times(1)
+            .with(eq(SYMBOL), always(), always(), always(), always())
+            .returning(|_,_,_,_,_| Ok(concat!(
+                r#"[{"a": 1,"p": "1.0","q": "1.0","f": 1,"l": 1,"T": 1498793709153,"m": true,"M": true },"#,
+                r#"{"a": 2,"p": "2.0","q": "1.0","f": 2,"l": 2,"T": 1498793709153,"m": true,"M": true }]"#
+            ).to_string()));
+        let base_url = spawn_server(mock_api).await;
+
+        let (status, body) = get(&format!("{}/prices?symbol={}&start={}&end={}", base_url, SYMBOL, START, END)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!([{ "timestamp": "2025-01-27T14:00:00.000Z", "price": 1.5 }]));
+    }
+
+    #[tokio::test]
+    async fn test_get_prices_rejects_missing_and_invalid_params() {
+        let base_url = spawn_server(MockBinanceAPI::new()).await;
+
+        let (status, body) = get(&format!("{}/prices?start={}&end={}", base_url, START, END)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "Missing parameter symbol");
+
+        let (status, _) = get(&format!("{}/prices?symbol={}&start=yesterday&end={}", base_url, SYMBOL, END)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get(&format!("{}/prices?symbol={}&start={}&end={}", base_url, SYMBOL, END, START)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = get(&format!("{}/prices?symbol=BTC-USDC&start={}&end={}", base_url, START, END)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "Invalid symbol \"BTC-USDC\"");
+    }
+
+    #[tokio::test]
+    async fn test_get_prices_returns_bad_gateway_when_binance_fails() {
+        let mut mock_api = MockBinanceAPI::new();
+        mock_api.expect_agg_trades()
+            .returning(|_,_,_,_,_| Err(anyhow::Error::msg("connection refused")));
+        let base_url = spawn_server(mock_api).await;
+
+        let (status, body) = get(&format!("{}/prices?symbol={}&start={}&end={}", base_url, SYMBOL, START, END)).await;
+
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(body["error"].as_str().unwrap().contains("connection refused"));
+    }
+
+    #[test]
+    fn test_get_prices_rejects_symbol_binance_does_not_list() {
+        let _m = mockito::mock("GET", "/api/v3/aggTrades")
+            .match_query(mockito::Matcher::Any)
+            .with_status(400)
+            .with_body(r#"{"code":-1121,"msg":"Invalid symbol."}"#)
+            .create();
+        // Kept out of the runtime, a blocking client can't be dropped in one
+        let client = Arc::new(BinanceHttpClient::with_base_url(&mockito::server_url()));
+        let provider = Arc::new(BinancePriceProvider::new(Box::new(client.clone())));
+
+        let (status, body) = tokio::runtime::Runtime::new().unwrap().block_on(async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, router(provider)).await.unwrap() });
+            get(&format!("http://{}/prices?symbol=NOTLISTED&start={}&end={}", addr, START, END)).await
+        });
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("Invalid symbol."), "{}", body);
+    }
+
+    #[test]
+    fn test_get_metrics_renders_client_and_redis_metrics() {
+        let _m = mockito::mock("GET", "/api/v3/ping")
+            .with_status(200)
+            .with_header("x-mbx-used-weight-1m", "42")
+            .with_body("{}")
+            .create();
+        // Made and kept out of the runtime, a blocking client can't be dropped in one
+        let client = Arc::new(BinanceHttpClient::with_base_url(&mockito::server_url()));
+        client.ping().unwrap();
+        // Nothing listens on port 1, Redis is reported down
+        let db = Arc::new(LocalDb::new("127.0.0.1", 1, None, false, std::time::Duration::from_millis(200)).unwrap());
+        let exporter = Arc::new(MetricsExporter::new(client.clone(), db).unwrap());
+
+        let body = tokio::runtime::Runtime::new().unwrap().block_on(async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, metrics_router(exporter)).await.unwrap() });
+            let resp = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap();
+            assert_eq!(resp.status().as_u16(), 200);
+            resp.text().await.unwrap()
+        });
+
+        for line in ["binance_requests_total 1", "binance_requests_failed 0", "binance_retries_total 0", "binance_last_used_weight 42", "redis_up 0"] {
+            assert!(body.lines().any(|l| l == line), "missing {:?} in {}", line, body);
+        }
+        assert!(body.contains("# TYPE binance_requests_total counter"));
+    }
+}